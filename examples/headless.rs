@@ -0,0 +1,65 @@
+// Plays a short scripted game through the library alone: no terminal, no
+// colours, just the engine's answers printed as plain text.
+use connect_dots_game::{Column, GameConfigBuilder, GameOutcome, Player, ThreatEvaluator};
+
+fn main() {
+    let opening = [4, 4, 3, 5, 2]
+        .into_iter()
+        .map(|number| Column::from_user(number).expect("the script stays on the board"))
+        .collect();
+    let mut game = GameConfigBuilder::new()
+        .moves(opening)
+        .build()
+        .expect("the script is a legal opening");
+
+    let view = game.board_view();
+    for row in 0..view.height() {
+        let line: String = (0..view.width())
+            .map(|col| match view.get(row, col) {
+                Some(Player::One) => 'X',
+                Some(Player::Two) => 'O',
+                _ => '.',
+            })
+            .collect();
+        println!("{}", line);
+    }
+
+    for player in [Player::One, Player::Two] {
+        println!("{:?} threatens {:?}", player, game.threat_squares(player));
+    }
+
+    let to_move = game.side_to_move();
+    let reply = game.principal_variation(1, &ThreatEvaluator)[0];
+    println!(
+        "{:?} to move, the bot plays column {}",
+        to_move,
+        reply.to_user()
+    );
+    game.play_move(reply)
+        .expect("the bot only suggests legal moves");
+
+    while game.outcome() == GameOutcome::Ongoing {
+        let mover = game.side_to_move();
+        let column = match game.winning_moves(mover).first() {
+            Some(&win) => win,
+            None => game.principal_variation(1, &ThreatEvaluator)[0],
+        };
+        game.play_move(column)
+            .expect("the bot only suggests legal moves");
+        println!("{:?} plays column {}", mover, column.to_user());
+    }
+
+    match game.outcome() {
+        GameOutcome::Win(winner) => println!(
+            "{:?} wins along {:?}",
+            winner,
+            game.winning_line().expect("a won game has its line")
+        ),
+        _ => println!("draw"),
+    }
+}
+
+#[test]
+fn plays_to_a_finish() {
+    main();
+}
@@ -0,0 +1,386 @@
+// Dots and Boxes for --mode dots, on a square of boxes. It shares nothing
+// with the Connect 4 engine beyond players and input.
+
+use crate::{
+    escape, read_stdin, GameError, GameMeta, Options, Player, CLEAR_SCREEN, ORANGE, RED, RESET,
+};
+
+const DOTS_BOXES: usize = 3;
+
+#[derive(Debug)]
+enum DotsError {
+    Malformed(String),
+    OutOfRange,
+    Taken,
+}
+
+impl std::fmt::Display for DotsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DotsError::Malformed(line) => write!(
+                f,
+                "'{}' isn't an edge, try 'h 1 2' or 'v 2 1' for a row and column",
+                line
+            ),
+            DotsError::OutOfRange => write!(f, "that edge isn't on the grid"),
+            DotsError::Taken => write!(f, "that edge is already drawn"),
+        }
+    }
+}
+
+// An edge from the dot at a 0-indexed row and column to the next dot right
+// of it or below it. Players number rows and columns from 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Edge {
+    Horizontal(usize, usize),
+    Vertical(usize, usize),
+}
+
+impl Edge {
+    fn parse(line: &str) -> Result<Edge, DotsError> {
+        let malformed = || DotsError::Malformed(line.trim().to_string());
+        let parts: Vec<&str> = line.split_whitespace().collect();
+
+        let [direction, row, col] = parts[..] else {
+            return Err(malformed());
+        };
+        let row: usize = row.parse().map_err(|_| malformed())?;
+        let col: usize = col.parse().map_err(|_| malformed())?;
+        let (row, col) = (
+            row.checked_sub(1).ok_or(DotsError::OutOfRange)?,
+            col.checked_sub(1).ok_or(DotsError::OutOfRange)?,
+        );
+
+        let edge = match direction {
+            "h" | "H" => Edge::Horizontal(row, col),
+            "v" | "V" => Edge::Vertical(row, col),
+            _ => return Err(malformed()),
+        };
+
+        let (rows, cols) = match edge {
+            Edge::Horizontal(..) => (DOTS_BOXES + 1, DOTS_BOXES),
+            Edge::Vertical(..) => (DOTS_BOXES, DOTS_BOXES + 1),
+        };
+        if row >= rows || col >= cols {
+            return Err(DotsError::OutOfRange);
+        }
+
+        Ok(edge)
+    }
+
+    // The boxes, by row and column, that this edge is a side of.
+    fn boxes(self) -> Vec<(usize, usize)> {
+        match self {
+            Edge::Horizontal(row, col) => [row.checked_sub(1), Some(row)]
+                .into_iter()
+                .flatten()
+                .filter(|&row| row < DOTS_BOXES)
+                .map(|row| (row, col))
+                .collect(),
+            Edge::Vertical(row, col) => [col.checked_sub(1), Some(col)]
+                .into_iter()
+                .flatten()
+                .filter(|&col| col < DOTS_BOXES)
+                .map(|col| (row, col))
+                .collect(),
+        }
+    }
+}
+
+struct DotsBoard {
+    horizontal: [[bool; DOTS_BOXES]; DOTS_BOXES + 1],
+    vertical: [[bool; DOTS_BOXES + 1]; DOTS_BOXES],
+    owners: [[Player; DOTS_BOXES]; DOTS_BOXES],
+    current_player: Player,
+}
+
+impl DotsBoard {
+    fn new() -> DotsBoard {
+        DotsBoard {
+            horizontal: [[false; DOTS_BOXES]; DOTS_BOXES + 1],
+            vertical: [[false; DOTS_BOXES + 1]; DOTS_BOXES],
+            owners: [[Player::None; DOTS_BOXES]; DOTS_BOXES],
+            current_player: Player::One,
+        }
+    }
+
+    fn drawn(&self, edge: Edge) -> bool {
+        match edge {
+            Edge::Horizontal(row, col) => self.horizontal[row][col],
+            Edge::Vertical(row, col) => self.vertical[row][col],
+        }
+    }
+
+    fn box_closed(&self, row: usize, col: usize) -> bool {
+        self.horizontal[row][col]
+            && self.horizontal[row + 1][col]
+            && self.vertical[row][col]
+            && self.vertical[row][col + 1]
+    }
+
+    // Draws `edge` for the side to move and gives them every box it closes.
+    // Closing a box earns another turn, otherwise the turn passes. Gives the
+    // number of boxes closed.
+    fn claim(&mut self, edge: Edge) -> Result<usize, DotsError> {
+        if self.drawn(edge) {
+            return Err(DotsError::Taken);
+        }
+
+        match edge {
+            Edge::Horizontal(row, col) => self.horizontal[row][col] = true,
+            Edge::Vertical(row, col) => self.vertical[row][col] = true,
+        }
+
+        let closed: Vec<(usize, usize)> = edge
+            .boxes()
+            .into_iter()
+            .filter(|&(row, col)| self.box_closed(row, col))
+            .collect();
+
+        for &(row, col) in &closed {
+            self.owners[row][col] = self.current_player;
+        }
+        if closed.is_empty() {
+            self.current_player = match self.current_player {
+                Player::One => Player::Two,
+                _ => Player::One,
+            };
+        }
+
+        Ok(closed.len())
+    }
+
+    fn score(&self, player: Player) -> usize {
+        self.owners
+            .iter()
+            .flatten()
+            .filter(|&&owner| owner == player)
+            .count()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.owners
+            .iter()
+            .flatten()
+            .all(|&owner| owner != Player::None)
+    }
+
+    // Whoever holds more boxes once they are all taken. An even split is a
+    // draw, which only an even number of boxes allows.
+    fn winner(&self) -> Player {
+        let (one, two) = (self.score(Player::One), self.score(Player::Two));
+
+        match one.cmp(&two) {
+            std::cmp::Ordering::Greater => Player::One,
+            std::cmp::Ordering::Less => Player::Two,
+            std::cmp::Ordering::Equal => Player::None,
+        }
+    }
+
+    // Dots joined by the drawn edges, with each closed box showing the
+    // number of the player who took it.
+    fn render(&self) -> String {
+        let glyph = |player: Player| match player {
+            Player::One => format!("{}1{}", escape(RED), escape(RESET)),
+            Player::Two => format!("{}2{}", escape(ORANGE), escape(RESET)),
+            Player::None => " ".to_string(),
+        };
+        let numbers: Vec<String> = (1..=DOTS_BOXES + 1).map(|col| col.to_string()).collect();
+        let mut lines = vec![format!("   {}", numbers.join("   "))];
+
+        for row in 0..=DOTS_BOXES {
+            let mut line = format!("{:<2} •", row + 1);
+            for col in 0..DOTS_BOXES {
+                line.push_str(if self.horizontal[row][col] {
+                    "───•"
+                } else {
+                    "   •"
+                });
+            }
+            lines.push(line);
+
+            if row == DOTS_BOXES {
+                break;
+            }
+
+            let mut line = "   ".to_string();
+            for col in 0..=DOTS_BOXES {
+                line.push(if self.vertical[row][col] { '│' } else { ' ' });
+                if col < DOTS_BOXES {
+                    line.push_str(&format!(" {} ", glyph(self.owners[row][col])));
+                }
+            }
+            lines.push(line.trim_end().to_string());
+        }
+
+        let mut frame = lines.join("\n");
+        frame.push('\n');
+        frame
+    }
+}
+
+// Plays Dots and Boxes until someone quits, naming the players as Connect 4
+// would.
+pub fn run_dots(options: &Options) -> Result<(), GameError> {
+    let meta = options.meta(GameMeta::default());
+    let name = |player: Player| match meta.name(player) {
+        Some(name) => name.to_string(),
+        None => format!("Player {}", player as u8),
+    };
+    let mut board = DotsBoard::new();
+    let mut message: Option<String> = None;
+
+    loop {
+        print!("{}{}", escape(CLEAR_SCREEN), board.render());
+        println!(
+            "Boxes: {} {}, {} {}",
+            name(Player::One),
+            board.score(Player::One),
+            name(Player::Two),
+            board.score(Player::Two)
+        );
+        if let Some(message) = message.take() {
+            println!("{}{}{}", escape(RED), message, escape(RESET));
+        }
+
+        if board.is_finished() {
+            match board.winner() {
+                Player::None => println!("{}It's a draw!{}", escape(ORANGE), escape(RESET)),
+                winner => println!("{}{} wins!{}", escape(ORANGE), name(winner), escape(RESET)),
+            }
+            println!("Press 'R' to restart or 'Q' to quit the game.");
+
+            match read_stdin()?.trim() {
+                "R" | "r" => board = DotsBoard::new(),
+                "Q" | "q" => return Ok(()),
+                _ => message = Some("invalid input".to_string()),
+            }
+            continue;
+        }
+
+        println!(
+            "{}: draw an edge as 'h ROW COL' or 'v ROW COL' from a dot, or 'Q' to quit:",
+            name(board.current_player).to_uppercase()
+        );
+
+        let line = read_stdin()?;
+        if line.trim().eq_ignore_ascii_case("q") {
+            println!("Quitting...");
+            return Ok(());
+        }
+
+        if let Err(err) = Edge::parse(&line).and_then(|edge| board.claim(edge)) {
+            message = Some(format!("Error: {}", err));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edges_parse_from_one_based_rows_and_columns() {
+        assert_eq!(Edge::parse("h 1 2").unwrap(), Edge::Horizontal(0, 1));
+        assert_eq!(Edge::parse("V 3 4").unwrap(), Edge::Vertical(2, 3));
+        assert!(matches!(Edge::parse("v 4 1"), Err(DotsError::OutOfRange)));
+        assert!(matches!(Edge::parse("h 0 1"), Err(DotsError::OutOfRange)));
+        assert!(matches!(Edge::parse("d 1 1"), Err(DotsError::Malformed(_))));
+        assert!(matches!(Edge::parse("h 1"), Err(DotsError::Malformed(_))));
+    }
+
+    #[test]
+    fn claiming_an_edge_draws_it_and_passes_the_turn() {
+        let mut board = DotsBoard::new();
+
+        assert_eq!(board.claim(Edge::Horizontal(0, 0)).unwrap(), 0);
+        assert!(board.drawn(Edge::Horizontal(0, 0)));
+        assert_eq!(board.current_player, Player::Two);
+
+        assert!(matches!(
+            board.claim(Edge::Horizontal(0, 0)),
+            Err(DotsError::Taken)
+        ));
+        assert_eq!(board.current_player, Player::Two);
+    }
+
+    #[test]
+    fn closing_a_box_takes_it_and_earns_another_turn() {
+        let mut board = DotsBoard::new();
+
+        for edge in [
+            Edge::Horizontal(0, 0),
+            Edge::Horizontal(1, 0),
+            Edge::Vertical(0, 0),
+        ] {
+            board.claim(edge).unwrap();
+        }
+        assert_eq!(board.current_player, Player::Two);
+
+        assert_eq!(board.claim(Edge::Vertical(0, 1)).unwrap(), 1);
+        assert_eq!(board.owners[0][0], Player::Two);
+        assert_eq!(board.current_player, Player::Two);
+        assert_eq!(board.score(Player::Two), 1);
+    }
+
+    #[test]
+    fn one_edge_can_close_two_boxes() {
+        let mut board = DotsBoard::new();
+
+        for edge in [
+            Edge::Horizontal(0, 0),
+            Edge::Horizontal(1, 0),
+            Edge::Vertical(0, 0),
+            Edge::Horizontal(0, 1),
+            Edge::Horizontal(1, 1),
+            Edge::Vertical(0, 2),
+        ] {
+            board.claim(edge).unwrap();
+        }
+        let mover = board.current_player;
+
+        assert_eq!(board.claim(Edge::Vertical(0, 1)).unwrap(), 2);
+        assert_eq!(board.score(mover), 2);
+        assert_eq!(board.current_player, mover);
+    }
+
+    #[test]
+    fn the_game_ends_with_the_most_boxes_winning() {
+        let mut board = DotsBoard::new();
+        let edges = (0..=DOTS_BOXES)
+            .flat_map(|row| (0..DOTS_BOXES).map(move |col| Edge::Horizontal(row, col)))
+            .chain(
+                (0..DOTS_BOXES)
+                    .flat_map(|row| (0..=DOTS_BOXES).map(move |col| Edge::Vertical(row, col))),
+            );
+
+        for edge in edges {
+            assert!(!board.is_finished());
+            board.claim(edge).unwrap();
+        }
+
+        let (one, two) = (board.score(Player::One), board.score(Player::Two));
+        assert!(board.is_finished());
+        assert_eq!(one + two, DOTS_BOXES * DOTS_BOXES);
+        assert_eq!(
+            board.winner(),
+            if one > two { Player::One } else { Player::Two }
+        );
+    }
+
+    #[test]
+    fn the_winner_holds_more_boxes() {
+        let mut board = DotsBoard::new();
+        board.owners = [[Player::Two; DOTS_BOXES]; DOTS_BOXES];
+        board.owners[0] = [Player::One; DOTS_BOXES];
+        board.owners[1][0] = Player::One;
+
+        assert!(board.is_finished());
+        assert_eq!(board.score(Player::One), 4);
+        assert_eq!(board.score(Player::Two), 5);
+        assert_eq!(board.winner(), Player::Two);
+
+        board.owners[1][1] = Player::One;
+        assert_eq!(board.winner(), Player::One);
+    }
+}
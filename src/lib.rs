@@ -0,0 +1,10522 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+mod dots;
+
+const RESET: &str = "\x1b[0m";
+const ORANGE: &str = "\x1b[93m";
+const RED: &str = "\x1b[0;31m";
+const CLEAR_SCREEN: &str = "\x1b[2J";
+const CENTER_BACKGROUND: &str = "\x1b[48;5;237m";
+const TUI_CLEAR: &str = "\x1b[2J\x1b[H";
+const MOUSE_ON: &str = "\x1b[?1000h\x1b[?1006h";
+const MOUSE_OFF: &str = "\x1b[?1000l\x1b[?1006l";
+const TUI_BUTTONS: [(&str, &str); 2] = [("[ Restart ]", "R"), ("[ Quit ]", "Q")];
+
+// Colours, screen clears and drop animation only make sense on a terminal.
+// Decided once from stdout unless --color or --no-color settles it first.
+static ANSI: OnceLock<bool> = OnceLock::new();
+
+fn ansi_for(output: &impl IsTerminal, flag: Option<bool>) -> bool {
+    flag.unwrap_or_else(|| output.is_terminal())
+}
+
+fn escape(code: &'static str) -> &'static str {
+    if *ANSI.get_or_init(|| ansi_for(&io::stdout(), None)) {
+        code
+    } else {
+        ""
+    }
+}
+
+// How much play prints. Quiet leaves only the result, verbose adds each
+// move's time and evaluation, and very verbose how hard the search worked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+enum Verbosity {
+    Quiet,
+    #[default]
+    Normal,
+    Verbose,
+    VeryVerbose,
+}
+
+// Set once from -v and --quiet before anything is printed.
+static VERBOSITY: OnceLock<Verbosity> = OnceLock::new();
+
+fn verbosity() -> Verbosity {
+    *VERBOSITY.get_or_init(Verbosity::default)
+}
+
+// Set once from --compact before anything is printed. Compact output leaves
+// out blank lines and the rules above the board, and asks for a move on
+// the status line instead of under a heading.
+static COMPACT: OnceLock<bool> = OnceLock::new();
+
+fn compact() -> bool {
+    *COMPACT.get_or_init(|| false)
+}
+
+// Positions tried by the forced-win search, for very verbose output.
+static SEARCH_NODES: AtomicU64 = AtomicU64::new(0);
+const DROP_FRAME_DELAY: Duration = Duration::from_millis(60);
+const PLAYBACK_DELAY: Duration = Duration::from_millis(800);
+const PLAYBACK_MIN_DELAY: Duration = Duration::from_millis(100);
+const PLAYBACK_MAX_DELAY: Duration = Duration::from_millis(6400);
+const FRAME_CELL_SIZE: usize = 40;
+const CELL_WIDTH: usize = 2;
+
+const BOARD_WIDTH: usize = 7;
+const BOARD_HEIGHT: usize = 6;
+
+type Board = [[u8; BOARD_WIDTH]; BOARD_HEIGHT];
+type FillTimes = [[Option<Duration>; BOARD_WIDTH]; BOARD_HEIGHT];
+
+const DIRECTIONS: [(isize, isize); 4] = [
+    (0, 1),  // horizontal
+    (1, 0),  // vertical
+    (1, 1),  // diagonal (top-left to bottom-right)
+    (-1, 1), // diagonal (bottom-left to top-right)
+];
+
+// Bit set over `DIRECTIONS`, in the same order, selecting which lines count
+// towards a win.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct WinDirections(u8);
+
+impl Default for WinDirections {
+    fn default() -> WinDirections {
+        WinDirections::ALL
+    }
+}
+
+impl WinDirections {
+    const HORIZONTAL: WinDirections = WinDirections(0b0001);
+    const VERTICAL: WinDirections = WinDirections(0b0010);
+    const DIAGONAL_DOWN: WinDirections = WinDirections(0b0100);
+    const DIAGONAL_UP: WinDirections = WinDirections(0b1000);
+    const ALL: WinDirections = WinDirections(
+        Self::HORIZONTAL.0 | Self::VERTICAL.0 | Self::DIAGONAL_DOWN.0 | Self::DIAGONAL_UP.0,
+    );
+
+    fn without(self, other: WinDirections) -> WinDirections {
+        WinDirections(self.0 & !other.0)
+    }
+
+    fn directions(self) -> Vec<(isize, isize)> {
+        DIRECTIONS
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.0 & (1 << i) != 0)
+            .map(|(_, &direction)| direction)
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Player {
+    One = 1,
+    Two = 2,
+    None = 0,
+}
+
+impl Player {
+    fn from_int(int: u8) -> Player {
+        match int {
+            1 => Player::One,
+            2 => Player::Two,
+            _ => Player::None,
+        }
+    }
+}
+
+// A 0-indexed board column. User-facing numbers are 1-indexed and only
+// cross into the engine through `from_user`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Column(pub usize);
+
+impl Column {
+    pub fn from_user(number: usize) -> Result<Column, MoveError> {
+        if (1..=BOARD_WIDTH).contains(&number) {
+            Ok(Column(number - 1))
+        } else {
+            Err(MoveError::InvalidColumn { max: BOARD_WIDTH })
+        }
+    }
+
+    pub fn to_user(self) -> usize {
+        self.0 + 1
+    }
+}
+
+#[derive(Debug)]
+pub enum MoveError {
+    GameFinished,
+    InvalidColumn { max: usize },
+    ColumnFull,
+    NothingToUndo,
+    NothingToRedo,
+    NoBounce,
+    DrawAlreadyOffered,
+    DrawOfferCooldown { moves: u8 },
+    NoDrawOffer,
+}
+
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveError::ColumnFull => write!(f, "column is full"),
+            MoveError::InvalidColumn { max } => write!(f, "column must be between 1 and {}", max),
+            MoveError::GameFinished => write!(f, "game is already finished"),
+            MoveError::NothingToUndo => write!(f, "no move to undo"),
+            MoveError::NothingToRedo => write!(f, "no move to redo"),
+            MoveError::NoBounce => write!(f, "column and both its neighbours are full"),
+            MoveError::DrawAlreadyOffered => write!(f, "a draw is already on offer"),
+            MoveError::DrawOfferCooldown { moves } => {
+                write!(f, "you can offer another draw in {} moves", moves)
+            }
+            MoveError::NoDrawOffer => write!(f, "no draw has been offered"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Tiebreak {
+    #[default]
+    Draw,
+    LongestRun,
+}
+
+// Swap-two opening. Seats are indexes into the move sources: the first seat
+// places three discs, then the second takes a colour or places two more and
+// hands the choice back.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Swap2 {
+    Placing { seat: usize, remaining: u8 },
+    Choosing { seat: usize, can_place: bool },
+    Done,
+}
+
+impl Swap2 {
+    fn start(enabled: bool, game: &Game) -> Swap2 {
+        if enabled && game.current_move == 0 {
+            Swap2::Placing {
+                seat: 0,
+                remaining: 3,
+            }
+        } else {
+            Swap2::Done
+        }
+    }
+
+    // Moves to the next phase once a disc has been placed.
+    fn placed(self) -> Swap2 {
+        match self {
+            Swap2::Placing { seat, remaining } if remaining > 1 => Swap2::Placing {
+                seat,
+                remaining: remaining - 1,
+            },
+            Swap2::Placing { seat, .. } => Swap2::Choosing {
+                seat: 1 - seat,
+                can_place: seat == 0,
+            },
+            other => other,
+        }
+    }
+
+    // Takes the choosing seat's answer: red or yellow settles the opening,
+    // with true when the seat took the other seat's colour, and placing
+    // more hands two discs to the chooser. None for anything not on offer.
+    fn choose(self, choice: Column) -> Option<(Swap2, bool)> {
+        match self {
+            Swap2::Choosing { seat, .. } if choice.0 < 2 => Some((Swap2::Done, choice.0 != seat)),
+            Swap2::Choosing {
+                seat,
+                can_place: true,
+            } if choice.0 == 2 => Some((Swap2::Placing { seat, remaining: 2 }, false)),
+            _ => None,
+        }
+    }
+}
+
+// Colours have no owners until the swap-two opening is settled, so the names
+// are taken off the game meanwhile and given back once colours are chosen.
+fn hold_names(game: &mut Game, swap2: Swap2) -> [Option<String>; 2] {
+    if swap2 == Swap2::Done {
+        return [None, None];
+    }
+
+    [game.meta.p1_name.take(), game.meta.p2_name.take()]
+}
+
+fn seat_name(names: &[Option<String>; 2], seat: usize) -> String {
+    names[seat]
+        .clone()
+        .unwrap_or_else(|| format!("Player {}", seat + 1))
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum RetryLimit {
+    #[default]
+    Forfeit,
+    Exit,
+}
+
+// Hooks that let a variant constrain moves and adjust the outcome without
+// growing `play_move`. `validate` runs before a disc is placed and
+// `post_apply` after the standard win check. Rules must be `Send` so a game
+// can be cloned onto another thread.
+trait Rules: Send {
+    fn validate(&self, game: &Game, column: Column) -> Result<(), MoveError>;
+
+    fn post_apply(&mut self, _game: &mut Game) {}
+
+    fn name(&self) -> String;
+
+    fn clone_box(&self) -> Box<dyn Rules>;
+}
+
+impl Clone for Box<dyn Rules> {
+    fn clone(&self) -> Box<dyn Rules> {
+        self.clone_box()
+    }
+}
+
+#[derive(Clone)]
+struct StandardRules;
+
+impl Rules for StandardRules {
+    fn validate(&self, game: &Game, column: Column) -> Result<(), MoveError> {
+        if game.is_finished {
+            return Err(MoveError::GameFinished);
+        }
+
+        let max = game.board_view().width();
+        if column.0 >= max {
+            return Err(MoveError::InvalidColumn { max });
+        }
+
+        match game.preview(game.bounce_landing(column)?) {
+            Some(_) => Ok(()),
+            None => Err(MoveError::ColumnFull),
+        }
+    }
+
+    fn name(&self) -> String {
+        "standard".to_string()
+    }
+
+    fn clone_box(&self) -> Box<dyn Rules> {
+        Box::new(self.clone())
+    }
+}
+
+// Standard play, except a full board without a four goes to whoever has the
+// longest run. Equal runs are still a draw.
+#[derive(Clone)]
+struct LongestRunRules;
+
+impl Rules for LongestRunRules {
+    fn validate(&self, game: &Game, column: Column) -> Result<(), MoveError> {
+        StandardRules.validate(game, column)
+    }
+
+    fn post_apply(&mut self, game: &mut Game) {
+        if !game.is_finished || game.winner != Player::None || !game.available_columns().is_empty()
+        {
+            return;
+        }
+
+        let one = game.longest_run(Player::One);
+        let two = game.longest_run(Player::Two);
+
+        if one > two {
+            game.winner = Player::One;
+        } else if two > one {
+            game.winner = Player::Two;
+        }
+    }
+
+    fn name(&self) -> String {
+        "longest-run".to_string()
+    }
+
+    fn clone_box(&self) -> Box<dyn Rules> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum FrameStyle {
+    #[default]
+    Ascii,
+    Box,
+    None,
+}
+
+// How the board is drawn. None of it changes play, so the settings travel
+// together from the command line into every position of the game.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct DisplayOptions {
+    grow_up: bool,
+    show_move_numbers: bool,
+    show_pressure: bool,
+    show_hud: bool,
+    show_threat_count: bool,
+    mark_center: bool,
+    frame_style: FrameStyle,
+    show_dead_cells: bool,
+    compact: bool,
+}
+
+// The side a disc dropped into a full column tries first under --bounce.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Bounce {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Tilt {
+    Left,
+    Right,
+}
+
+// Wraps another rule set and tilts the board after every move. The settle
+// can make or break a four, so the outcome is worked out again from scratch
+// before the wrapped rules get their say.
+#[derive(Clone)]
+struct TiltRules {
+    tilt: Tilt,
+    inner: Box<dyn Rules>,
+}
+
+impl Rules for TiltRules {
+    fn validate(&self, game: &Game, column: Column) -> Result<(), MoveError> {
+        self.inner.validate(game, column)
+    }
+
+    fn post_apply(&mut self, game: &mut Game) {
+        let mover = game.last_mover();
+
+        game.settle_tilt(self.tilt);
+        game.rejudge(mover);
+        self.inner.post_apply(game);
+    }
+
+    fn name(&self) -> String {
+        let tilt = match self.tilt {
+            Tilt::Left => "left",
+            Tilt::Right => "right",
+        };
+
+        format!("tilt-{}+{}", tilt, self.inner.name())
+    }
+
+    fn clone_box(&self) -> Box<dyn Rules> {
+        Box::new(self.clone())
+    }
+}
+
+// Wraps another rule set and swaps the colours every other round: moves 3-4,
+// 7-8 and so on drop the opponent's colour. A four wins for the player whose
+// colour it is, whoever placed the discs.
+#[derive(Clone)]
+struct ChaosRules {
+    inner: Box<dyn Rules>,
+}
+
+impl Rules for ChaosRules {
+    fn validate(&self, game: &Game, column: Column) -> Result<(), MoveError> {
+        self.inner.validate(game, column)
+    }
+
+    fn post_apply(&mut self, game: &mut Game) {
+        let mover = game.last_mover();
+        let swapped = (game.current_move - 1) / 2 % 2 == 1;
+
+        if swapped {
+            if let Some((row, column)) = game.last_landing() {
+                game.board[row][column] = match mover {
+                    Player::One => Player::Two as u8,
+                    _ => Player::One as u8,
+                };
+            }
+
+            game.rejudge(mover);
+        }
+
+        self.inner.post_apply(game);
+    }
+
+    fn name(&self) -> String {
+        format!("chaos+{}", self.inner.name())
+    }
+
+    fn clone_box(&self) -> Box<dyn Rules> {
+        Box::new(self.clone())
+    }
+}
+
+// Scores a position for `player`, higher being better for them, so other
+// heuristics can be tried in the search without touching it.
+pub trait Evaluator {
+    fn evaluate(&self, game: &Game, player: Player) -> i32;
+}
+
+// The built-in evaluation: threats by parity, then centre control.
+pub struct ThreatEvaluator;
+
+impl Evaluator for ThreatEvaluator {
+    fn evaluate(&self, game: &Game, player: Player) -> i32 {
+        match player {
+            Player::Two => -game.evaluate(),
+            _ => game.evaluate(),
+        }
+    }
+}
+
+// Named tactical shapes. Rows are counted from the top like the board, and
+// converted to bottom-up numbering only for display.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Pattern {
+    Seven { row: usize, col: usize },
+    BaselineDoubleThreat { col: usize },
+    OddEvenThreat { row: usize, col: usize },
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Seven { row, col } => write!(
+                f,
+                "a 7 trap cornered at row {}, column {}",
+                BOARD_HEIGHT - row,
+                col + 1
+            ),
+            Pattern::BaselineDoubleThreat { col } => write!(
+                f,
+                "a baseline double threat in columns {}-{}",
+                col + 1,
+                col + 5
+            ),
+            Pattern::OddEvenThreat { row, col } => write!(
+                f,
+                "a row {} threat in column {} waiting on parity",
+                BOARD_HEIGHT - row,
+                col + 1
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ThreatChange {
+    Created,
+    Blocked,
+    Converted,
+}
+
+// A threat square appearing or being filled, recorded against the move that
+// caused it. Rows are counted from the top like `Pattern`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ThreatEvent {
+    move_number: usize,
+    player: Player,
+    row: usize,
+    col: usize,
+    change: ThreatChange,
+}
+
+impl std::fmt::Display for ThreatEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let player = match self.player {
+            Player::Two => "P2",
+            _ => "P1",
+        };
+        let (row, col) = (BOARD_HEIGHT - self.row, self.col + 1);
+
+        match self.change {
+            ThreatChange::Created => write!(
+                f,
+                "Move {}: {} threatens row {}, column {}",
+                self.move_number, player, row, col
+            ),
+            ThreatChange::Blocked => write!(
+                f,
+                "Move {}: {}'s threat at row {}, column {} is blocked",
+                self.move_number, player, row, col
+            ),
+            ThreatChange::Converted => write!(
+                f,
+                "Move {}: {} wins at row {}, column {}",
+                self.move_number, player, row, col
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameOutcome {
+    Ongoing,
+    Win(Player),
+    Draw,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Phase {
+    Opening,
+    Midgame,
+    Endgame,
+}
+
+impl std::fmt::Display for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Phase::Opening => write!(f, "opening"),
+            Phase::Midgame => write!(f, "midgame"),
+            Phase::Endgame => write!(f, "endgame"),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum GameError {
+    Io(io::Error),
+    InputClosed,
+    NoTrainingPosition(usize),
+}
+
+impl std::fmt::Display for GameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameError::Io(err) => write!(f, "failed to read input: {}", err),
+            GameError::InputClosed => write!(f, "input was closed"),
+            GameError::NoTrainingPosition(playouts) => write!(
+                f,
+                "no training position turned up in {} playouts under these rules",
+                playouts
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for GameError {
+    fn from(err: io::Error) -> GameError {
+        GameError::Io(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum DiagramError {
+    WrongDimensions,
+    InvalidCell(char),
+    WrongDiscCount,
+    Invalid(ValidationError),
+}
+
+impl std::fmt::Display for DiagramError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiagramError::WrongDimensions => {
+                write!(f, "diagram must have 6 rows of 7 cells")
+            }
+            DiagramError::InvalidCell(cell) => write!(f, "invalid cell '{}'", cell),
+            DiagramError::WrongDiscCount => {
+                write!(f, "disc counts don't match alternating turns")
+            }
+            DiagramError::Invalid(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ValidationError {
+    WrongDimensions(usize, usize),
+    WrongLength(usize),
+    InvalidCell(u8),
+    FloatingDisc,
+    WrongMoveCount,
+    WrongTurn,
+    ImpossibleWinner,
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::WrongDimensions(width, height) => write!(
+                f,
+                "board must be {}x{}, not {}x{}",
+                BOARD_WIDTH, BOARD_HEIGHT, width, height
+            ),
+            ValidationError::WrongLength(length) => {
+                write!(f, "{} bytes don't match the board size", length)
+            }
+            ValidationError::InvalidCell(cell) => write!(f, "invalid cell value {}", cell),
+            ValidationError::FloatingDisc => write!(f, "disc is not supported from below"),
+            ValidationError::WrongMoveCount => {
+                write!(f, "disc count doesn't match the move number")
+            }
+            ValidationError::WrongTurn => {
+                write!(f, "wrong player to move for the disc counts")
+            }
+            ValidationError::ImpossibleWinner => {
+                write!(f, "winner could not have made the last move")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    InvalidPosition(DiagramError),
+    NoWinDirections,
+    InvalidWinLength(u8),
+    InvalidMove(usize, MoveError),
+    HandicapNeedsEmptyBoard,
+    InvalidHandicap(usize, MoveError),
+    WinningHandicap,
+    CenterGravityWithTilt,
+    MercyWithoutTurnLimit,
+    MercyWithMovingDiscs,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::InvalidPosition(err) => write!(f, "invalid starting position: {}", err),
+            ConfigError::NoWinDirections => write!(f, "at least one win direction is needed"),
+            ConfigError::InvalidWinLength(length) => {
+                write!(f, "a line of {} doesn't fit on the board", length)
+            }
+            ConfigError::InvalidMove(index, err) => {
+                write!(f, "move {} can't be played: {}", index + 1, err)
+            }
+            ConfigError::HandicapNeedsEmptyBoard => {
+                write!(f, "a handicap needs an empty starting board")
+            }
+            ConfigError::InvalidHandicap(index, err) => {
+                write!(f, "handicap disc {} can't be dropped: {}", index + 1, err)
+            }
+            ConfigError::WinningHandicap => write!(f, "the handicap discs already connect"),
+            ConfigError::CenterGravityWithTilt => {
+                write!(f, "center gravity can't be combined with a tilt")
+            }
+            ConfigError::MercyWithoutTurnLimit => write!(f, "the mercy rule needs a turn limit"),
+            ConfigError::MercyWithMovingDiscs => {
+                write!(f, "the mercy rule can't be combined with a tilt or chaos")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum DecodeError {
+    Truncated,
+    TrailingBytes,
+    InvalidColumn(usize, usize),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Truncated => write!(f, "encoded moves are truncated"),
+            DecodeError::TrailingBytes => write!(f, "unexpected bytes after the encoded moves"),
+            DecodeError::InvalidColumn(index, column) => write!(
+                f,
+                "move {} is column {}, but the board has {} columns",
+                index + 1,
+                column + 1,
+                BOARD_WIDTH
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum LoadError {
+    Io(io::Error),
+    Truncated,
+    BadMagic,
+    UnsupportedVersion(u8),
+    WrongDimensions(u8, u8),
+    InvalidCell(u8),
+    InvalidPosition(DiagramError),
+    InvalidEncoding(DecodeError),
+    InvalidMove(usize, MoveError),
+    InvalidHeader(String),
+    InvalidRecord(SgfError),
+    Rejected(ConfigError),
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "failed to read save: {}", err),
+            LoadError::Truncated => write!(f, "save file is truncated"),
+            LoadError::BadMagic => write!(f, "not a connect 4 save file"),
+            LoadError::UnsupportedVersion(version) => {
+                write!(f, "unsupported save version {}", version)
+            }
+            LoadError::WrongDimensions(width, height) => {
+                write!(f, "unsupported board size {}x{}", width, height)
+            }
+            LoadError::InvalidCell(cell) => write!(f, "invalid cell value {}", cell),
+            LoadError::InvalidPosition(err) => write!(f, "invalid starting position: {}", err),
+            LoadError::InvalidEncoding(err) => write!(f, "invalid move encoding: {}", err),
+            LoadError::InvalidMove(index, err) => {
+                write!(f, "move {} can't be replayed: {}", index + 1, err)
+            }
+            LoadError::InvalidHeader(line) => write!(f, "invalid header line '{}'", line),
+            LoadError::InvalidRecord(err) => write!(f, "invalid game record: {}", err),
+            LoadError::Rejected(err) => write!(f, "game doesn't fit the chosen rules: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> LoadError {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => LoadError::Truncated,
+            _ => LoadError::Io(err),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum SgfError {
+    Malformed(String),
+    NotConnectFour,
+    UnsupportedSize(String),
+    InvalidValue(String, String),
+    InvalidPosition(DiagramError),
+    WrongPlayer(usize),
+    InvalidMove(usize, MoveError),
+    Rejected(ConfigError),
+}
+
+impl std::fmt::Display for SgfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SgfError::Malformed(reason) => write!(f, "malformed record: {}", reason),
+            SgfError::NotConnectFour => write!(f, "not a connect 4 record (missing GM[connect4])"),
+            SgfError::UnsupportedSize(size) => write!(f, "unsupported board size {}", size),
+            SgfError::InvalidValue(property, value) => {
+                write!(f, "invalid {} value '{}'", property, value)
+            }
+            SgfError::InvalidPosition(err) => write!(f, "invalid starting position: {}", err),
+            SgfError::WrongPlayer(index) => {
+                write!(f, "move {} is tagged with the wrong player", index + 1)
+            }
+            SgfError::InvalidMove(index, err) => {
+                write!(f, "move {} can't be played: {}", index + 1, err)
+            }
+            SgfError::Rejected(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+const WIN_SCORE: i32 = 1000;
+const EVAL_BAR_RANGE: i32 = 20;
+const EVAL_BAR_WIDTH: usize = 20;
+const PRESSURE_RANGE: i32 = 8;
+const PRESSURE_GAUGE_WIDTH: usize = 10;
+const EVAL_CACHE_CAPACITY: usize = 4096;
+const INACCURACY_MARGIN: i32 = 4;
+const WIN_LENGTH: u8 = 4;
+const FORCED_WIN_DEPTH: u8 = 3;
+const KEY_MOMENT_SWING: i32 = 6;
+const PV_DEPTH: u8 = 8;
+const DRAW_OFFER_COOLDOWN: u8 = 6;
+const INPUT_HISTORY_CAPACITY: usize = 50;
+const TRAIN_MIN_PLIES: u8 = 4;
+const TRAIN_MAX_PLIES: u8 = 30;
+const TRAIN_MAX_PLAYOUTS: usize = 10_000;
+
+const SAVE_MAGIC: &[u8; 4] = b"C4SV";
+const SAVE_VERSION: u8 = 3;
+const MOVE_BITS: usize = 3;
+const SAVE_PATH: &str = "connect4.sav";
+const AUTOSAVE_PATH: &str = "connect4.autosave";
+const RATED_LOG_PATH: &str = "connect4.rated.log";
+
+// Each challenge is a position where the side to move has a winning drop.
+const CHALLENGES: [(&str, &str); 4] = [
+    (
+        "horizontal",
+        ".......
+         .......
+         .......
+         .......
+         O......
+         XXX.OO.",
+    ),
+    (
+        "vertical",
+        ".......
+         .......
+         .......
+         X......
+         X.O....
+         X.OO...",
+    ),
+    (
+        "diagonal",
+        ".......
+         .......
+         .......
+         ..XO...
+         .XOX...
+         XOXO.O.",
+    ),
+    (
+        "yellow",
+        ".......
+         .......
+         .......
+         .......
+         X..X...
+         XX.OOO.",
+    ),
+];
+
+// Read-only access to the board by (row, col), rows counted from the top,
+// so callers don't depend on how cells are stored.
+#[derive(Clone, Copy)]
+pub struct BoardView<'a> {
+    board: &'a Board,
+}
+
+impl BoardView<'_> {
+    pub fn width(&self) -> usize {
+        BOARD_WIDTH
+    }
+
+    pub fn height(&self) -> usize {
+        BOARD_HEIGHT
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<Player> {
+        self.board
+            .get(row)?
+            .get(col)
+            .map(|&cell| Player::from_int(cell))
+    }
+
+    // One column's cells from the bottom up, empty cells included.
+    pub fn column(&self, col: usize) -> Vec<Player> {
+        self.board
+            .iter()
+            .rev()
+            .map(|cells| Player::from_int(cells[col]))
+            .collect()
+    }
+
+    // Discs stacked in `col`. Gravity keeps them contiguous from the bottom.
+    pub fn column_height(&self, col: usize) -> usize {
+        self.column(col)
+            .iter()
+            .take_while(|&&cell| cell != Player::None)
+            .count()
+    }
+
+    // Every cell in reading order: top row first, left to right.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize, Player)> + '_ {
+        self.board.iter().enumerate().flat_map(|(row, cells)| {
+            cells
+                .iter()
+                .enumerate()
+                .map(move |(col, &cell)| (row, col, Player::from_int(cell)))
+        })
+    }
+}
+
+// Chess-style time control. A side's budget only runs down during its own
+// turn, and `increment` is added back after every move it makes.
+#[derive(Clone, Copy, Debug)]
+struct Clock {
+    remaining: [Duration; 2],
+    increment: Duration,
+}
+
+impl Clock {
+    // Parses `mm:ss` with an optional `+inc` in seconds, e.g. `5:00+3`.
+    fn parse(spec: &str) -> Result<Clock, String> {
+        let (base, increment) = spec.split_once('+').unwrap_or((spec, "0"));
+        let (minutes, seconds) = base
+            .split_once(':')
+            .ok_or_else(|| format!("invalid clock '{}': expected mm:ss+inc", spec))?;
+        let parse = |field: &str| {
+            field
+                .parse::<u64>()
+                .map_err(|err| format!("invalid clock '{}': {}", spec, err))
+        };
+
+        let budget = Duration::from_secs(parse(minutes)? * 60 + parse(seconds)?);
+
+        Ok(Clock {
+            remaining: [budget; 2],
+            increment: Duration::from_secs(parse(increment)?),
+        })
+    }
+
+    // Charges a move's thinking time to `player`. Returns false when their
+    // flag falls, otherwise credits the increment.
+    fn charge(&mut self, player: Player, elapsed: Duration) -> bool {
+        let remaining = &mut self.remaining[(player == Player::Two) as usize];
+
+        match remaining.checked_sub(elapsed) {
+            Some(left) if !left.is_zero() => {
+                *remaining = left + self.increment;
+                true
+            }
+            _ => {
+                *remaining = Duration::ZERO;
+                false
+            }
+        }
+    }
+
+    fn render(&self) -> String {
+        let [one, two] = self.remaining.map(|time| {
+            let seconds = time.as_secs();
+            format!("{:02}:{:02}", seconds / 60, seconds % 60)
+        });
+
+        format!("P1 {} | P2 {}", one, two)
+    }
+}
+
+// Optional details stored in a save's header as `key: value` lines. Saves
+// from before the header existed load with everything unset.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct GameMeta {
+    p1_name: Option<String>,
+    p2_name: Option<String>,
+    date: Option<String>,
+    result: Option<String>,
+    note: Option<String>,
+}
+
+impl GameMeta {
+    fn name(&self, player: Player) -> Option<&str> {
+        match player {
+            Player::One => self.p1_name.as_deref(),
+            Player::Two => self.p2_name.as_deref(),
+            Player::None => None,
+        }
+    }
+
+    fn to_header(&self) -> String {
+        let fields = [
+            ("p1", &self.p1_name),
+            ("p2", &self.p2_name),
+            ("date", &self.date),
+            ("result", &self.result),
+            ("note", &self.note),
+        ];
+
+        fields
+            .iter()
+            .filter_map(|(key, value)| value.as_ref().map(|value| (key, value)))
+            .map(|(key, value)| format!("{}: {}\n", key, value.replace('\n', " ")))
+            .collect()
+    }
+
+    // Unknown keys are skipped so newer saves still load.
+    fn from_header(header: &str) -> Result<GameMeta, LoadError> {
+        let mut meta = GameMeta::default();
+
+        for line in header.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| LoadError::InvalidHeader(line.to_string()))?;
+            let value = Some(value.trim().to_string());
+
+            match key.trim() {
+                "p1" => meta.p1_name = value,
+                "p2" => meta.p2_name = value,
+                "date" => meta.date = value,
+                "result" => meta.result = value,
+                "note" => meta.note = value,
+                _ => {}
+            }
+        }
+
+        Ok(meta)
+    }
+}
+
+// Converts days since 1970-01-01 to a (year, month, day) civil date, using
+// Howard Hinnant's `civil_from_days`.
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+
+fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_date((seconds / 86_400) as i64);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// Each player's threat squares, kept up to date as discs land so the
+// evaluation reads them without rescanning the board. `windows` counts how
+// many lines each empty cell is the only gap in; a cell is a threat square
+// while its count is above zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct ThreatCounts {
+    windows: [[[u8; BOARD_WIDTH]; BOARD_HEIGHT]; 2],
+    odd: [usize; 2],
+    even: [usize; 2],
+}
+
+impl ThreatCounts {
+    // Adds or takes away one line's share. A line counts for a player when
+    // it holds their discs in every cell but one, which is empty.
+    fn tally(&mut self, board: &Board, window: &[(usize, usize)], add: bool) {
+        let mut empty = window.iter().filter(|&&(row, col)| board[row][col] == 0);
+        let (Some(&(row, col)), None) = (empty.next(), empty.next()) else {
+            return;
+        };
+
+        for seat in 0..2 {
+            let disc = seat as u8 + 1;
+            let owned = window.iter().filter(|&&(r, c)| board[r][c] == disc).count();
+
+            if owned != window.len() - 1 {
+                continue;
+            }
+
+            let count = &mut self.windows[seat][row][col];
+            let was_square = *count > 0;
+            if add {
+                *count += 1;
+            } else {
+                *count -= 1;
+            }
+
+            if was_square != (*count > 0) {
+                let parity = if (BOARD_HEIGHT - row) % 2 == 1 {
+                    &mut self.odd
+                } else {
+                    &mut self.even
+                };
+                if add {
+                    parity[seat] += 1;
+                } else {
+                    parity[seat] -= 1;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Game {
+    current_move: u8,
+    current_player: Player,
+    board: Board,
+    is_finished: bool,
+    winner: Player,
+    history: Vec<Column>,
+    redo: Vec<Column>,
+    start_board: Board,
+    last_error: Option<String>,
+    locked_column: Option<Column>,
+    rules: Box<dyn Rules>,
+    win_directions: WinDirections,
+    win_length_orthogonal: u8,
+    win_length_diagonal: u8,
+    exact_length: bool,
+    wrap: bool,
+    center_gravity: bool,
+    bounce: Option<Bounce>,
+    fill_order: Vec<Column>,
+    turn_limit: Option<u8>,
+    komi: Option<f64>,
+    mercy: Option<u8>,
+    display: DisplayOptions,
+    threats: ThreatCounts,
+    draw_cooldown: u8,
+    // The player whose draw offer awaits an answer, and the move each
+    // player last offered on.
+    draw_offer: Option<Player>,
+    draw_offered_at: [Option<u8>; 2],
+    move_numbers: Board,
+    started: Instant,
+    fill_times: FillTimes,
+    clock: Option<Clock>,
+    meta: GameMeta,
+}
+
+// Fails to compile if a field ever stops `Game` from moving across threads.
+const _: fn() = || {
+    fn assert_send<T: Send + Clone>() {}
+    assert_send::<Game>();
+};
+
+// Games are equal when their positions are: board, move count, side to move
+// and result. History, rules, settings and transient UI state are left out,
+// so two move orders reaching the same position compare and hash equal.
+impl PartialEq for Game {
+    fn eq(&self, other: &Game) -> bool {
+        self.position_key() == other.position_key()
+    }
+}
+
+impl Eq for Game {}
+
+impl std::hash::Hash for Game {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.position_key().hash(state);
+    }
+}
+
+impl Default for Game {
+    fn default() -> Game {
+        Game {
+            current_move: 0,
+            current_player: Player::One,
+            board: [
+                [0, 0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0, 0],
+            ],
+            is_finished: false,
+            winner: Player::None,
+            history: Vec::new(),
+            redo: Vec::new(),
+            start_board: [[0; BOARD_WIDTH]; BOARD_HEIGHT],
+            last_error: None,
+            locked_column: None,
+            rules: Box::new(StandardRules),
+            win_directions: WinDirections::default(),
+            win_length_orthogonal: WIN_LENGTH,
+            win_length_diagonal: WIN_LENGTH,
+            exact_length: false,
+            wrap: false,
+            center_gravity: false,
+            bounce: None,
+            fill_order: center_out_columns(),
+            turn_limit: None,
+            komi: None,
+            mercy: None,
+            display: DisplayOptions::default(),
+            threats: ThreatCounts::default(),
+            draw_cooldown: DRAW_OFFER_COOLDOWN,
+            draw_offer: None,
+            draw_offered_at: [None; 2],
+            move_numbers: [[0; BOARD_WIDTH]; BOARD_HEIGHT],
+            started: Instant::now(),
+            fill_times: [[None; BOARD_WIDTH]; BOARD_HEIGHT],
+            clock: None,
+            meta: GameMeta::default(),
+        }
+    }
+}
+
+impl Game {
+    fn from_diagram(diagram: &str) -> Result<Game, DiagramError> {
+        let rows: Vec<&str> = diagram.lines().map(str::trim).collect();
+
+        if rows.len() != BOARD_HEIGHT {
+            return Err(DiagramError::WrongDimensions);
+        }
+
+        let mut board: Board = [[0; BOARD_WIDTH]; BOARD_HEIGHT];
+
+        for (row, line) in rows.iter().enumerate() {
+            if line.chars().count() != BOARD_WIDTH {
+                return Err(DiagramError::WrongDimensions);
+            }
+
+            for (col, cell) in line.chars().enumerate() {
+                board[row][col] = match cell {
+                    'X' => Player::One as u8,
+                    'O' => Player::Two as u8,
+                    '.' => 0,
+                    _ => return Err(DiagramError::InvalidCell(cell)),
+                };
+            }
+        }
+
+        Game::from_board(board)
+    }
+
+    fn from_board(board: Board) -> Result<Game, DiagramError> {
+        let count = |player: Player| {
+            board
+                .iter()
+                .flatten()
+                .filter(|&&cell| cell == player as u8)
+                .count()
+        };
+        let (ones, twos) = (count(Player::One), count(Player::Two));
+
+        // A board of only Player 1 discs is a handicap start, so Player 2 is
+        // on turn however many there are.
+        let current_player = if ones == twos {
+            Player::One
+        } else if ones == twos + 1 || twos == 0 {
+            Player::Two
+        } else {
+            return Err(DiagramError::WrongDiscCount);
+        };
+
+        Game::from_parts(board, current_player, (ones + twos) as u8).map_err(DiagramError::Invalid)
+    }
+
+    // Reads a flat row-major array of cell values, top row first, with 0 for
+    // empty and 1 or 2 for a player's disc, as other tools might write it.
+    fn from_bytes(
+        bytes: impl AsRef<[u8]>,
+        width: usize,
+        height: usize,
+    ) -> Result<Game, ValidationError> {
+        let bytes = bytes.as_ref();
+
+        if width != BOARD_WIDTH || height != BOARD_HEIGHT {
+            return Err(ValidationError::WrongDimensions(width, height));
+        }
+        if bytes.len() != width * height {
+            return Err(ValidationError::WrongLength(bytes.len()));
+        }
+
+        let mut board = [[0; BOARD_WIDTH]; BOARD_HEIGHT];
+
+        for (cell, &byte) in board.iter_mut().flatten().zip(bytes) {
+            if byte > 2 {
+                return Err(ValidationError::InvalidCell(byte));
+            }
+            *cell = byte;
+        }
+
+        let count = |player: Player| bytes.iter().filter(|&&byte| byte == player as u8).count();
+        let (ones, twos) = (count(Player::One), count(Player::Two));
+        let current_player = if ones > twos {
+            Player::Two
+        } else {
+            Player::One
+        };
+
+        Game::from_parts(board, current_player, (ones + twos) as u8)
+    }
+
+    // The one safe way to build an arbitrary position: checks gravity, that
+    // the disc counts fit the move number and turn, and that at most the
+    // player who just moved has a four. Winner and finished are re-derived.
+    fn from_parts(
+        board: Board,
+        current_player: Player,
+        current_move: u8,
+    ) -> Result<Game, ValidationError> {
+        let floating = board.windows(2).any(|rows| {
+            rows[0]
+                .iter()
+                .zip(rows[1])
+                .any(|(&above, below)| above != 0 && below == 0)
+        });
+
+        if floating {
+            return Err(ValidationError::FloatingDisc);
+        }
+
+        let count = |player: Player| {
+            board
+                .iter()
+                .flatten()
+                .filter(|&&cell| cell == player as u8)
+                .count()
+        };
+        let (ones, twos) = (count(Player::One), count(Player::Two));
+
+        if ones + twos != current_move as usize {
+            return Err(ValidationError::WrongMoveCount);
+        }
+
+        let expected = if ones == twos {
+            Player::One
+        } else if ones == twos + 1 || twos == 0 {
+            Player::Two
+        } else {
+            return Err(ValidationError::WrongMoveCount);
+        };
+
+        if current_player != expected {
+            return Err(ValidationError::WrongTurn);
+        }
+
+        let mut game = Game {
+            current_move,
+            current_player,
+            board,
+            start_board: board,
+            ..Game::default()
+        };
+
+        // A line for the side on turn also covers both sides having one.
+        if game.connects(current_player) {
+            return Err(ValidationError::ImpossibleWinner);
+        }
+
+        game.winner = game.calculate_winner();
+        game.threats = game.count_threats();
+
+        Ok(game)
+    }
+
+    // Growing up hides the empty rows above the highest disc, keeping at
+    // least the bottom row so an empty board still shows something.
+    fn top_row(&self) -> usize {
+        if self.display.grow_up {
+            self.board
+                .iter()
+                .position(|row| row.iter().any(|&cell| cell != 0))
+                .unwrap_or(BOARD_HEIGHT - 1)
+        } else {
+            0
+        }
+    }
+
+    fn render_board(&self) -> String {
+        let mut lines = Vec::new();
+        let header_rule = if self.display.compact {
+            None
+        } else {
+            render_separator(self.display.frame_style)
+        };
+
+        lines.extend(header_rule.clone());
+        let mut details = vec![
+            format!("Move {}", self.current_move),
+            self.phase().to_string(),
+            format!("{:.0}% full", self.fill_ratio() * 100.0),
+        ];
+        if self.is_symmetric() {
+            details.push("symmetric position".to_string());
+        }
+        if let Some(limit) = self.turn_limit {
+            match limit.saturating_sub(self.current_move) {
+                1 => details.push("1 move left".to_string()),
+                left => details.push(format!("{} moves left", left)),
+            }
+        }
+        if self.display.show_dead_cells && !self.is_finished && self.is_dead_draw() {
+            details.push("no wins left".to_string());
+        }
+
+        lines.push(format!(
+            "{}CONNECT 4 ({}){}",
+            escape(ORANGE),
+            details.join(", "),
+            escape(RESET)
+        ));
+        if let Some(clock) = &self.clock {
+            lines.push(format!(
+                "{}Clock: {}{}",
+                escape(ORANGE),
+                clock.render(),
+                escape(RESET)
+            ));
+        }
+        lines.push(format!(
+            "{}P1 longest: {} | P2 longest: {}{}",
+            escape(ORANGE),
+            self.longest_run(Player::One),
+            self.longest_run(Player::Two),
+            escape(RESET)
+        ));
+        if self.display.show_pressure {
+            lines.push(format!(
+                "Pressure: P1 {} P2",
+                render_gauge(
+                    self.pressure(Player::One),
+                    PRESSURE_RANGE,
+                    PRESSURE_GAUGE_WIDTH
+                )
+            ));
+        }
+        if self.display.show_hud {
+            lines.push(self.render_hud());
+        }
+        lines.extend(header_rule);
+        if let Some(locked) = self.locked_column {
+            let markers: Vec<&str> = (0..BOARD_WIDTH)
+                .map(|column| if column == locked.0 { "▼" } else { "" })
+                .collect();
+            lines.push(format!(
+                "{}{}{}",
+                escape(ORANGE),
+                render_row(&markers),
+                escape(RESET)
+            ));
+        }
+        lines.push(render_column_numbers(self.display.mark_center));
+
+        let top_row = self.top_row();
+
+        let dead_cells = if self.display.show_dead_cells {
+            self.dead_cells()
+        } else {
+            Vec::new()
+        };
+
+        // Discs from the starting position have no move number and keep
+        // their glyph.
+        for (index, (row, numbers)) in self.board[top_row..]
+            .iter()
+            .zip(&self.move_numbers[top_row..])
+            .enumerate()
+        {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(numbers)
+                .map(|(&cell, &number)| match (cell, number) {
+                    (1, number) if self.display.show_move_numbers && number > 0 => {
+                        format!("{}{}{}", escape(RED), number, escape(RESET))
+                    }
+                    (2, number) if self.display.show_move_numbers && number > 0 => {
+                        format!("{}{}{}", escape(ORANGE), number, escape(RESET))
+                    }
+                    (1, _) => "🔴".to_string(),
+                    (2, _) => "🟡".to_string(),
+                    _ => "⚫".to_string(),
+                })
+                .enumerate()
+                .map(|(col, cell)| {
+                    if dead_cells.contains(&(top_row + index, col)) {
+                        "⚪".to_string()
+                    } else {
+                        cell
+                    }
+                })
+                .enumerate()
+                .map(|(col, cell)| {
+                    if self.display.mark_center && center_columns().contains(&col) {
+                        shade_center(&cell)
+                    } else {
+                        cell
+                    }
+                })
+                .collect();
+
+            lines.push(render_row(&cells));
+        }
+
+        lines.extend(render_separator(self.display.frame_style));
+
+        if self.is_finished {
+            match (self.winner, self.meta().name(self.winner)) {
+                (Player::One, Some(name)) => lines.push(format!(
+                    "{}{} (🔴) wins!{}",
+                    escape(ORANGE),
+                    name,
+                    escape(RESET)
+                )),
+                (Player::Two, Some(name)) => lines.push(format!(
+                    "{}{} (🟡) wins!{}",
+                    escape(ORANGE),
+                    name,
+                    escape(RESET)
+                )),
+                (Player::One, None) => lines.push(format!(
+                    "{}🔴 Player 1 has won!{}",
+                    escape(ORANGE),
+                    escape(RESET)
+                )),
+                (Player::Two, None) => lines.push(format!(
+                    "{}🟡 Player 2 has won!{}",
+                    escape(ORANGE),
+                    escape(RESET)
+                )),
+                (Player::None, _) => {
+                    lines.push(format!("{}It's a draw!{}", escape(ORANGE), escape(RESET)))
+                }
+            }
+
+            lines.extend(render_separator(self.display.frame_style));
+        }
+
+        let mut frame = lines.join("\n");
+        frame.push('\n');
+        frame
+    }
+
+    // The coach's view for --hud, redrawn with every frame: the evaluation
+    // bar, how many threat squares each side has and the phase.
+    fn render_hud(&self) -> String {
+        let eval = self.evaluate();
+
+        format!(
+            "HUD: P1 {} P2 ({:+}) | Threats: P1 {}, P2 {} | {}",
+            render_gauge(eval, EVAL_BAR_RANGE, EVAL_BAR_WIDTH),
+            eval,
+            self.threat_squares(Player::One).len(),
+            self.threat_squares(Player::Two).len(),
+            self.phase()
+        )
+    }
+
+    // The board with each side's threat squares marked, including ones that
+    // aren't playable yet because the square below them is still empty.
+    fn render_threat_overlay(&self) -> String {
+        let one = self.threat_squares(Player::One);
+        let two = self.threat_squares(Player::Two);
+        let mut lines = vec![render_column_numbers(self.display.mark_center)];
+
+        for (row, cells) in self.board.iter().enumerate() {
+            let row_str: Vec<&str> = cells
+                .iter()
+                .enumerate()
+                .map(|(col, &cell)| {
+                    let square = (row, col);
+
+                    match (cell, one.contains(&square), two.contains(&square)) {
+                        (1, _, _) => "🔴",
+                        (2, _, _) => "🟡",
+                        (_, true, true) => "🟧",
+                        (_, true, false) => "🟥",
+                        (_, false, true) => "🟨",
+                        _ => "⚫",
+                    }
+                })
+                .collect();
+
+            lines.push(render_row(&row_str));
+        }
+
+        lines.push(format!(
+            "{}🟥 P1 threat | 🟨 P2 threat | 🟧 both{}",
+            escape(ORANGE),
+            escape(RESET)
+        ));
+
+        let mut overlay = lines.join("\n");
+        overlay.push('\n');
+        overlay
+    }
+
+    // The name given for `player`, or "Player N" when there is none.
+    fn player_name(&self, player: Player) -> String {
+        match self.meta.name(player) {
+            Some(name) => name.to_string(),
+            None => format!("Player {}", player as u8),
+        }
+    }
+
+    // The board in words for screen readers, one sentence per line: each
+    // column from the bottom up, then the turn and any threats. Rows are
+    // counted from the bottom, as they are read out.
+    fn describe(&self) -> String {
+        let view = self.board_view();
+        let colour = |player: Player| match player {
+            Player::One => "red",
+            Player::Two => "yellow",
+            Player::None => "empty",
+        };
+        let mut lines = Vec::new();
+
+        for col in 0..view.width() {
+            // Empty cells above the top disc are only counted. Centre
+            // gravity can leave gaps below it, and those are named.
+            let cells = view.column(col);
+            let top = cells
+                .iter()
+                .rposition(|&cell| cell != Player::None)
+                .map_or(0, |index| index + 1);
+            let discs: Vec<&str> = cells[..top].iter().map(|&cell| colour(cell)).collect();
+
+            let description = match (discs.len(), view.height() - discs.len()) {
+                (0, _) => "empty".to_string(),
+                (_, 0) => format!("{}, full", discs.join(", ")),
+                (_, empty) => format!("{}, then {} empty", discs.join(", "), empty),
+            };
+
+            lines.push(format!("Column {}: {}.", col + 1, description));
+        }
+
+        match self.side_to_move() {
+            Player::None => lines.push("The game is over.".to_string()),
+            player => lines.push(format!(
+                "{} to move, playing {}.",
+                self.player_name(player),
+                colour(player)
+            )),
+        }
+
+        for player in [Player::One, Player::Two] {
+            let squares: Vec<String> = self
+                .threat_squares(player)
+                .into_iter()
+                .map(|(row, col)| format!("column {} row {}", col + 1, view.height() - row))
+                .collect();
+
+            if !squares.is_empty() {
+                lines.push(format!(
+                    "{} threatens {}.",
+                    self.player_name(player),
+                    squares.join(", ")
+                ));
+            }
+        }
+
+        let mut description = lines.join("\n");
+        description.push('\n');
+        description
+    }
+
+    fn render_status(&self) -> String {
+        let turn = match self.side_to_move() {
+            Player::None => "Game over".to_string(),
+            player => format!("{} to move", self.player_name(player)),
+        };
+        let mut status = format!("{} | Move {}", turn, self.current_move);
+
+        // Counted afresh for every frame, so a threat made or spoilt by the
+        // last move shows straight away.
+        if self.display.show_threat_count {
+            status.push_str(&format!(
+                " | Threats — P1: {}  P2: {}",
+                self.threat_squares(Player::One).len(),
+                self.threat_squares(Player::Two).len()
+            ));
+        }
+
+        match &self.last_error {
+            Some(error) => format!(
+                "{} | {}Error: {}{}",
+                status,
+                escape(RED),
+                error,
+                escape(RESET)
+            ),
+            None => status,
+        }
+    }
+
+    fn write_board<W: Write>(&self, out: &mut W, level: Verbosity) -> io::Result<()> {
+        if level == Verbosity::Quiet {
+            return Ok(());
+        }
+
+        if tui() {
+            return self.write_tui_frame(out);
+        }
+
+        writeln!(
+            out,
+            "{}{}{}",
+            escape(CLEAR_SCREEN),
+            self.render_board(),
+            self.render_status()
+        )?;
+        out.flush()
+    }
+
+    // The TUI always draws from the top-left corner, with a button bar on the
+    // first row, so clicks can be traced back to what was under them.
+    fn write_tui_frame<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let board = self.render_board();
+        let lines: Vec<&str> = board.lines().collect();
+        let numbers = render_column_numbers(self.display.mark_center);
+        let buttons: Vec<&str> = TUI_BUTTONS.iter().map(|&(label, _)| label).collect();
+
+        // Terminal rows are 1-based and the button bar takes the first.
+        let rows = BOARD_HEIGHT - self.top_row();
+        let board_rows = lines
+            .iter()
+            .position(|&line| line == numbers)
+            .map(|index| index + 2..index + 3 + rows);
+
+        *TUI_LAYOUT.lock().expect("TUI layout lock poisoned") = TuiLayout {
+            board_rows: board_rows.unwrap_or(0..0),
+            buttons: self.is_finished,
+        };
+
+        writeln!(
+            out,
+            "{}{}\n{}{}",
+            TUI_CLEAR,
+            if self.is_finished {
+                buttons.join("  ")
+            } else {
+                String::new()
+            },
+            board,
+            self.render_status()
+        )?;
+        out.flush()
+    }
+
+    fn display_board(&self) {
+        let stdout = io::stdout();
+        let mut handle = stdout.lock();
+
+        self.write_board(&mut handle, verbosity())
+            .expect("Failed to write board");
+    }
+
+    fn animate_drop(&self, column: Column, landing_row: usize) {
+        if escape(CLEAR_SCREEN).is_empty() {
+            return;
+        }
+
+        // Centre gravity can land a disc below others, so it only shows
+        // passing through empty cells.
+        for row in (0..landing_row).filter(|&row| self.board[row][column.0] == 0) {
+            let mut frame = self.clone();
+            frame.board[row][column.0] = self.current_player as u8;
+            frame.display_board();
+
+            thread::sleep(DROP_FRAME_DELAY);
+        }
+    }
+
+    fn display_error(&mut self, error: String) {
+        self.last_error = Some(error);
+        self.display_board();
+        self.last_error = None;
+    }
+
+    // Orthogonal lines and diagonals can need different lengths; the
+    // classic game uses four for both.
+    fn win_length(&self, row_step: isize, col_step: isize) -> u8 {
+        if row_step == 0 || col_step == 0 {
+            self.win_length_orthogonal
+        } else {
+            self.win_length_diagonal
+        }
+    }
+
+    // The cell at `row`, `col`, if it is on the board. With wrapping the
+    // columns join up into a cylinder, so only the rows have edges.
+    fn cell_at(&self, row: isize, col: isize) -> Option<(usize, usize)> {
+        let col = if self.wrap {
+            col.rem_euclid(BOARD_WIDTH as isize)
+        } else {
+            col
+        };
+
+        if row >= 0 && row < BOARD_HEIGHT as isize && col >= 0 && col < BOARD_WIDTH as isize {
+            Some((row as usize, col as usize))
+        } else {
+            None
+        }
+    }
+
+    // Length of the whole run through the disc at `row`, `col` along one
+    // direction. A run can't be longer than the board is wide, which stops
+    // a row filled all the way round a wrapped board from counting forever.
+    fn run_length(&self, row: usize, col: usize, row_step: isize, col_step: isize) -> u8 {
+        let target = self.board[row][col];
+        let mut consecutive_count = 1;
+
+        for sign in [-1, 1] {
+            let mut r = row as isize + sign * row_step;
+            let mut c = col as isize + sign * col_step;
+
+            while consecutive_count < BOARD_WIDTH as u8 {
+                match self.cell_at(r, c) {
+                    Some((r, c)) if self.board[r][c] == target => consecutive_count += 1,
+                    _ => break,
+                }
+                r += sign * row_step;
+                c += sign * col_step;
+            }
+        }
+
+        consecutive_count
+    }
+
+    fn connects(&self, player: Player) -> bool {
+        let target = player as u8;
+
+        if player == Player::None {
+            return false;
+        }
+
+        for row in 0..BOARD_HEIGHT {
+            for col in 0..BOARD_WIDTH {
+                if self.board[row][col] != target {
+                    continue;
+                }
+
+                for (row_step, col_step) in self.win_directions.directions() {
+                    let consecutive_count = self.run_length(row, col, row_step, col_step);
+                    let length = self.win_length(row_step, col_step);
+                    // Under exact length, overlines don't count.
+                    if consecutive_count == length
+                        || (consecutive_count > length && !self.exact_length)
+                    {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    // The first four cells of the winner's line, worked out from the board
+    // each time so an undo clears it and a redo brings it back. Cells run
+    // from the line's left end, or its top for a vertical one. A win shorter
+    // than four, under a short win length, has no line to give.
+    pub fn winning_line(&self) -> Option<[(usize, usize); 4]> {
+        if !self.is_finished || self.winner == Player::None {
+            return None;
+        }
+
+        let target = self.winner as u8;
+
+        for row in 0..BOARD_HEIGHT {
+            for col in 0..BOARD_WIDTH {
+                if self.board[row][col] != target {
+                    continue;
+                }
+
+                for (row_step, col_step) in self.win_directions.directions() {
+                    let length = self.win_length(row_step, col_step);
+                    let consecutive_count = self.run_length(row, col, row_step, col_step);
+                    let starts_here = self
+                        .cell_at(row as isize - row_step, col as isize - col_step)
+                        .is_none_or(|(r, c)| self.board[r][c] != target);
+
+                    if !starts_here
+                        || consecutive_count < length.max(4)
+                        || (consecutive_count > length && self.exact_length)
+                    {
+                        continue;
+                    }
+
+                    let mut line = [(row, col); 4];
+                    for (step, cell) in line.iter_mut().enumerate().skip(1) {
+                        let step = step as isize;
+                        *cell = self
+                            .cell_at(
+                                row as isize + step * row_step,
+                                col as isize + step * col_step,
+                            )
+                            .expect("the run continues along the line");
+                    }
+
+                    return Some(line);
+                }
+            }
+        }
+
+        None
+    }
+
+    // Who has a line on the board, if anyone, leaving the game untouched.
+    fn winner_if_any(&self) -> Option<Player> {
+        // A line needs at least as many discs as its shortest length.
+        // Assuming strict alternation here would miss wins for the side not
+        // on turn, which `winning_moves` tries out hypothetically.
+        if self.current_move < self.win_length_orthogonal.min(self.win_length_diagonal) {
+            return None;
+        }
+
+        [Player::One, Player::Two]
+            .into_iter()
+            .find(|&player| self.connects(player))
+    }
+
+    fn calculate_winner(&mut self) -> Player {
+        if let Some(player) = self.winner_if_any() {
+            self.is_finished = true;
+            return player;
+        }
+
+        // Only reached without a line, so a four that fills the last cell is
+        // a win rather than a draw. The board itself is checked rather than
+        // the move count, which a starting position may not agree with.
+        if self.available_columns().is_empty() {
+            self.is_finished = true;
+        }
+
+        Player::None
+    }
+
+    // Drops and slides discs one cell at a time until nothing moves. Falling
+    // takes priority, so discs end up piled into the tilted bottom corner.
+    fn settle_tilt(&mut self, tilt: Tilt) {
+        let step = match tilt {
+            Tilt::Left => -1,
+            Tilt::Right => 1,
+        };
+
+        loop {
+            let mut moved = false;
+
+            for row in (0..BOARD_HEIGHT).rev() {
+                for col in 0..BOARD_WIDTH {
+                    let cell = self.board[row][col];
+
+                    if cell == 0 {
+                        continue;
+                    }
+
+                    let side = col as isize + step;
+                    let target = if row + 1 < BOARD_HEIGHT && self.board[row + 1][col] == 0 {
+                        (row + 1, col)
+                    } else if (0..BOARD_WIDTH as isize).contains(&side)
+                        && self.board[row][side as usize] == 0
+                    {
+                        (row, side as usize)
+                    } else {
+                        continue;
+                    };
+
+                    self.board[row][col] = 0;
+                    self.board[target.0][target.1] = cell;
+                    self.move_numbers[target.0][target.1] = self.move_numbers[row][col];
+                    self.move_numbers[row][col] = 0;
+                    self.fill_times[target.0][target.1] = self.fill_times[row][col].take();
+                    moved = true;
+                }
+            }
+
+            if !moved {
+                break;
+            }
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        BOARD_WIDTH * BOARD_HEIGHT
+    }
+
+    fn filled(&self) -> usize {
+        self.current_move as usize
+    }
+
+    fn fill_ratio(&self) -> f32 {
+        self.filled() as f32 / self.capacity() as f32
+    }
+
+    // Splits the game into thirds of the board's capacity.
+    fn phase(&self) -> Phase {
+        let capacity = self.capacity();
+        let filled = self.filled() * 3;
+
+        if filled < capacity {
+            Phase::Opening
+        } else if filled < capacity * 2 {
+            Phase::Midgame
+        } else {
+            Phase::Endgame
+        }
+    }
+
+    fn mirror(&self) -> Board {
+        let mut mirrored = self.board;
+
+        for row in mirrored.iter_mut() {
+            row.reverse();
+        }
+
+        mirrored
+    }
+
+    fn is_symmetric(&self) -> bool {
+        self.mirror() == self.board
+    }
+
+    // A casual "who's pressing" number from `player`'s side: two points for
+    // each threat square more than the opponent has, one for each disc more
+    // in the centre, and one for having the move.
+    fn pressure(&self, player: Player) -> i32 {
+        let opponent = match player {
+            Player::One => Player::Two,
+            Player::Two => Player::One,
+            Player::None => return 0,
+        };
+        let threats = |player: Player| self.threat_squares(player).len() as i32;
+        let centre = |player: Player| {
+            self.board
+                .iter()
+                .flat_map(|row| center_columns().into_iter().map(move |col| row[col]))
+                .filter(|&cell| cell == player as u8)
+                .count() as i32
+        };
+        let tempo = match self.side_to_move() {
+            side if side == player => 1,
+            side if side == opponent => -1,
+            _ => 0,
+        };
+
+        2 * (threats(player) - threats(opponent)) + centre(player) - centre(opponent) + tempo
+    }
+
+    // Empty cells no line can ever be completed through: every window of the
+    // winning length covering them already holds discs of both colours.
+    fn dead_cells(&self) -> Vec<(usize, usize)> {
+        let mut cells = Vec::new();
+
+        for row in 0..BOARD_HEIGHT {
+            for col in 0..BOARD_WIDTH {
+                if self.board[row][col] != 0 {
+                    continue;
+                }
+
+                let live =
+                    self.win_directions
+                        .directions()
+                        .into_iter()
+                        .any(|(row_step, col_step)| {
+                            let length = self.win_length(row_step, col_step) as isize;
+
+                            (0..length).any(|offset| {
+                                let start_row = row as isize - row_step * offset;
+                                let start_col = col as isize - col_step * offset;
+                                let window: Vec<u8> = (0..length)
+                                    .filter_map(|i| {
+                                        self.cell_at(
+                                            start_row + row_step * i,
+                                            start_col + col_step * i,
+                                        )
+                                    })
+                                    .map(|(r, c)| self.board[r][c])
+                                    .collect();
+
+                                window.len() == length as usize
+                                    && !(window.contains(&1) && window.contains(&2))
+                            })
+                        });
+
+                if !live {
+                    cells.push((row, col));
+                }
+            }
+        }
+
+        cells
+    }
+
+    // Once every empty cell is dead neither side can connect, whatever is
+    // played, so the game is already a draw on lines.
+    fn is_dead_draw(&self) -> bool {
+        let empty = self
+            .board
+            .iter()
+            .flatten()
+            .filter(|&&cell| cell == 0)
+            .count();
+
+        empty > 0 && self.dead_cells().len() == empty
+    }
+
+    pub fn threat_squares(&self, player: Player) -> Vec<(usize, usize)> {
+        let target = player as u8;
+        let mut squares = Vec::new();
+
+        if player == Player::None {
+            return squares;
+        }
+
+        for row in 0..BOARD_HEIGHT as isize {
+            for col in 0..BOARD_WIDTH as isize {
+                for (row_step, col_step) in self.win_directions.directions() {
+                    let length = self.win_length(row_step, col_step) as usize;
+                    let window: Vec<(usize, usize)> = (0..length as isize)
+                        .filter_map(|i| self.cell_at(row + row_step * i, col + col_step * i))
+                        .collect();
+
+                    if window.len() != length {
+                        continue;
+                    }
+
+                    let owned = window
+                        .iter()
+                        .filter(|&&(r, c)| self.board[r][c] == target)
+                        .count();
+                    let empty: Vec<&(usize, usize)> = window
+                        .iter()
+                        .filter(|&&(r, c)| self.board[r][c] == 0)
+                        .collect();
+
+                    if owned == length - 1 && empty.len() == 1 {
+                        squares.push(*empty[0]);
+                    }
+                }
+            }
+        }
+
+        squares.sort();
+        squares.dedup();
+        squares
+    }
+
+    fn detect_patterns(&self, player: Player) -> Vec<Pattern> {
+        let target = player as u8;
+        let mut patterns = Vec::new();
+
+        if player == Player::None {
+            return patterns;
+        }
+
+        let owned = |row: isize, col: isize| {
+            row >= 0
+                && row < BOARD_HEIGHT as isize
+                && col >= 0
+                && col < BOARD_WIDTH as isize
+                && self.board[row as usize][col as usize] == target
+        };
+
+        // A horizontal bar of three whose end also starts a diagonal of three
+        // running down and back under the bar, in either orientation.
+        for row in 0..BOARD_HEIGHT as isize {
+            for col in 0..BOARD_WIDTH as isize {
+                for step in [-1, 1] {
+                    let shape = [
+                        (row, col),
+                        (row, col + step),
+                        (row, col + 2 * step),
+                        (row + 1, col + step),
+                        (row + 2, col + 2 * step),
+                    ];
+
+                    if shape.iter().all(|&(r, c)| owned(r, c)) {
+                        patterns.push(Pattern::Seven {
+                            row: row as usize,
+                            col: col as usize,
+                        });
+                    }
+                }
+            }
+        }
+
+        let baseline = self.board[BOARD_HEIGHT - 1];
+
+        for (col, window) in baseline.windows(5).enumerate() {
+            if window == [0, target, target, target, 0] {
+                patterns.push(Pattern::BaselineDoubleThreat { col });
+            }
+        }
+
+        let favoured_parity = if player == Player::One { 1 } else { 0 };
+
+        for (row, col) in self.threat_squares(player) {
+            let row_from_bottom = BOARD_HEIGHT - row;
+            let floating = row + 1 < BOARD_HEIGHT && self.board[row + 1][col] == 0;
+
+            if floating && row_from_bottom % 2 == favoured_parity {
+                patterns.push(Pattern::OddEvenThreat { row, col });
+            }
+        }
+
+        patterns
+    }
+
+    // Counts threat squares on odd and even rows, numbered from the bottom.
+    // The first player profits from odd threats and the second from even ones.
+    // The counts are the running ones `play_move` keeps, so this is cheap.
+    fn threat_parity(&self, player: Player) -> (usize, usize) {
+        match player {
+            Player::None => (0, 0),
+            _ => {
+                let seat = player as usize - 1;
+                (self.threats.odd[seat], self.threats.even[seat])
+            }
+        }
+    }
+
+    // The line of win length from `row`, `col` along one direction, or
+    // `None` if it runs off the board.
+    fn window(
+        &self,
+        row: isize,
+        col: isize,
+        row_step: isize,
+        col_step: isize,
+    ) -> Option<Vec<(usize, usize)>> {
+        let length = self.win_length(row_step, col_step) as usize;
+        let window: Vec<(usize, usize)> = (0..length as isize)
+            .filter_map(|i| self.cell_at(row + row_step * i, col + col_step * i))
+            .collect();
+
+        (window.len() == length).then_some(window)
+    }
+
+    // Threat counts for the whole board, from scratch.
+    fn count_threats(&self) -> ThreatCounts {
+        let mut threats = ThreatCounts::default();
+
+        for row in 0..BOARD_HEIGHT as isize {
+            for col in 0..BOARD_WIDTH as isize {
+                for (row_step, col_step) in self.win_directions.directions() {
+                    if let Some(window) = self.window(row, col, row_step, col_step) {
+                        threats.tally(&self.board, &window, true);
+                    }
+                }
+            }
+        }
+
+        threats
+    }
+
+    // Takes the lines through one cell out of the threat counts, or puts
+    // them back in. Called either side of the cell changing, it keeps the
+    // counts right without looking at the rest of the board.
+    fn mark_threats(&mut self, row: usize, col: usize, add: bool) {
+        let mut starts = Vec::new();
+
+        for (row_step, col_step) in self.win_directions.directions() {
+            let length = self.win_length(row_step, col_step) as isize;
+
+            for i in 0..length {
+                let start = self.cell_at(row as isize - row_step * i, col as isize - col_step * i);
+                if let Some((start_row, start_col)) = start {
+                    starts.push((start_row, start_col, row_step, col_step));
+                }
+            }
+        }
+
+        // A wrapped line longer than the board reaches a cell twice.
+        starts.sort();
+        starts.dedup();
+
+        for (start_row, start_col, row_step, col_step) in starts {
+            if let Some(window) =
+                self.window(start_row as isize, start_col as isize, row_step, col_step)
+            {
+                self.threats.tally(&self.board, &window, add);
+            }
+        }
+    }
+
+    // Static evaluation from Player 1's point of view: positive favours
+    // Player 1. Parity-correct threats weigh the most since they decide
+    // most endgames, then other threats and centre control.
+    fn evaluate(&self) -> i32 {
+        match self.winner {
+            Player::One => return WIN_SCORE,
+            Player::Two => return -WIN_SCORE,
+            Player::None if self.is_finished => return 0,
+            Player::None => {}
+        }
+
+        let (one_odd, one_even) = self.threat_parity(Player::One);
+        let (two_odd, two_even) = self.threat_parity(Player::Two);
+
+        let center = BOARD_WIDTH / 2;
+        let center_control: i32 = self
+            .board
+            .iter()
+            .map(|row| match Player::from_int(row[center]) {
+                Player::One => 1,
+                Player::Two => -1,
+                Player::None => 0,
+            })
+            .sum();
+
+        4 * one_odd as i32 + one_even as i32 - two_odd as i32 - 4 * two_even as i32 + center_control
+    }
+
+    // There is no solver, so a move is valued by the static evaluation after
+    // the opponent's strongest reply. The side to move is in zugzwang when
+    // every move scores below the position as it stands.
+    fn is_zugzwang(&self) -> bool {
+        if self.is_finished {
+            return false;
+        }
+
+        let sign = if self.current_player == Player::One {
+            1
+        } else {
+            -1
+        };
+        let current = sign * self.evaluate();
+
+        self.ordered_moves(self.current_player)
+            .iter()
+            .all(|&column| {
+                let mut after = self.clone();
+                after
+                    .play_move(column)
+                    .expect("available column is playable");
+
+                let value = if after.is_finished {
+                    sign * after.evaluate()
+                } else {
+                    after
+                        .available_columns()
+                        .iter()
+                        .map(|&reply| {
+                            let mut position = after.clone();
+                            position
+                                .play_move(reply)
+                                .expect("available column is playable");
+                            sign * position.evaluate()
+                        })
+                        .min()
+                        .unwrap_or(current)
+                };
+
+                value < current
+            })
+    }
+
+    // Everything needed to reproduce a position, one `key: value` per line so
+    // reports from different versions can be diffed.
+    fn debug_report(&self) -> String {
+        let fingerprint = self.board_fingerprint();
+        let start = Game::from_board(self.start_board)
+            .map(|game| game.board_fingerprint())
+            .unwrap_or_else(|err| format!("invalid ({})", err));
+        let history: Vec<String> = self
+            .history
+            .iter()
+            .map(|column| column.to_user().to_string())
+            .collect();
+
+        let mut lines = vec![format!(
+            "connect4 debug report v{}",
+            env!("CARGO_PKG_VERSION")
+        )];
+        lines.push("board:".to_string());
+        lines.extend(
+            fingerprint
+                .split(':')
+                .next()
+                .unwrap_or_default()
+                .split('/')
+                .map(|row| format!("  {}", row)),
+        );
+        lines.push(format!("move: {}", self.current_move));
+        lines.push(format!("current_player: {}", self.current_player as u8));
+        lines.push(format!("finished: {}", self.is_finished));
+        lines.push(format!("winner: {}", self.winner as u8));
+        if history.is_empty() {
+            lines.push("history: none".to_string());
+        } else {
+            lines.push(format!("history: {}", history.join(" ")));
+        }
+        lines.push(format!("start: {}", start));
+        lines.push(format!("rules: {}", self.rules.name()));
+        lines.push(format!("win_directions: {:04b}", self.win_directions.0));
+        lines.push(format!(
+            "win_lengths: {},{}",
+            self.win_length_orthogonal, self.win_length_diagonal
+        ));
+        lines.push(format!("exact_length: {}", self.exact_length));
+        lines.push(format!("wrap: {}", self.wrap));
+        lines.push(format!("center_gravity: {}", self.center_gravity));
+        let fill_order: Vec<String> = self
+            .fill_order
+            .iter()
+            .map(|column| column.to_user().to_string())
+            .collect();
+        lines.push(format!("fill_order: {}", fill_order.join(",")));
+        lines.push(format!(
+            "bounce: {}",
+            match self.bounce {
+                Some(Bounce::Left) => "left",
+                Some(Bounce::Right) => "right",
+                None => "none",
+            }
+        ));
+        lines.push(format!(
+            "turn_limit: {}",
+            self.turn_limit
+                .map_or("none".to_string(), |limit| limit.to_string())
+        ));
+        lines.push(format!(
+            "komi: {}",
+            self.komi
+                .map_or("none".to_string(), |komi| komi.to_string())
+        ));
+        lines.push(format!(
+            "mercy: {}",
+            self.mercy
+                .map_or("none".to_string(), |margin| margin.to_string())
+        ));
+        lines.push(format!(
+            "score: {} - {}",
+            self.score(Player::One),
+            self.score(Player::Two)
+        ));
+        lines.push(format!(
+            "clock: {}",
+            self.clock
+                .map_or("none".to_string(), |clock| clock.render())
+        ));
+        lines.push(format!("meta: {:?}", self.meta));
+        lines.push(format!(
+            "last_error: {}",
+            self.last_error.as_deref().unwrap_or("none")
+        ));
+        lines.push(format!("fingerprint: {}", fingerprint));
+
+        let mut report = lines.join("\n");
+        report.push('\n');
+        report
+    }
+
+    fn position_key(&self) -> (Board, u8, Player, bool, Player) {
+        (
+            self.board,
+            self.current_move,
+            self.current_player,
+            self.is_finished,
+            self.winner,
+        )
+    }
+
+    pub fn board_view(&self) -> BoardView<'_> {
+        BoardView { board: &self.board }
+    }
+
+    // Rows in the notation `from_diagram` reads, top row first.
+    fn diagram_rows(&self) -> Vec<String> {
+        let view = self.board_view();
+        let mut rows = vec![String::new(); view.height()];
+
+        for (row, _, player) in view.cells() {
+            rows[row].push(match player {
+                Player::One => 'X',
+                Player::Two => 'O',
+                Player::None => '.',
+            });
+        }
+
+        rows
+    }
+
+    // An SGF-style record for exchange with other tools: a root node with
+    // the game, board size, win lengths and any starting discs and names,
+    // then one node per move tagged R or Y with its column numbered from 1.
+    fn to_sgf(&self) -> String {
+        let mut position = self.position_at(0);
+        let mut record = format!(
+            "(;GM[connect4]SZ[{}:{}]WL[{}:{}]",
+            BOARD_WIDTH, BOARD_HEIGHT, self.win_length_orthogonal, self.win_length_diagonal
+        );
+
+        if self.start_board.iter().flatten().any(|&cell| cell != 0) {
+            record.push_str(&format!("SP[{}]", position.diagram_rows().join("/")));
+        }
+        for (property, name) in [("PR", &self.meta.p1_name), ("PY", &self.meta.p2_name)] {
+            if let Some(name) = name {
+                record.push_str(&format!("{}[{}]", property, escape_sgf(name)));
+            }
+        }
+
+        for &column in &self.history {
+            let tag = match position.current_player {
+                Player::Two => 'Y',
+                _ => 'R',
+            };
+
+            record.push_str(&format!("\n;{}[{}]", tag, column.to_user()));
+            position
+                .play_move(column)
+                .expect("history only contains legal moves");
+        }
+
+        record.push_str(")\n");
+        record
+    }
+
+    // Reads a record written by `to_sgf`. Unknown root properties are
+    // skipped, as SGF readers do, but every move must be tagged with the
+    // player whose turn it is.
+    fn from_sgf(text: &str) -> Result<Game, SgfError> {
+        let nodes = parse_sgf_nodes(text)?;
+        let (root, moves) = nodes
+            .split_first()
+            .ok_or_else(|| SgfError::Malformed("the record has no nodes".to_string()))?;
+
+        let mut config = GameConfigBuilder::new();
+        let mut meta = GameMeta::default();
+        let mut connect_four = false;
+
+        for (property, value) in root {
+            let invalid = || SgfError::InvalidValue(property.clone(), value.clone());
+
+            match property.as_str() {
+                "GM" => connect_four = value == "connect4",
+                "SZ" if *value != format!("{}:{}", BOARD_WIDTH, BOARD_HEIGHT) => {
+                    return Err(SgfError::UnsupportedSize(value.clone()));
+                }
+                "WL" => {
+                    let (orthogonal, diagonal) = value.split_once(':').ok_or_else(invalid)?;
+                    config = config.win_lengths(
+                        orthogonal.parse().map_err(|_| invalid())?,
+                        diagonal.parse().map_err(|_| invalid())?,
+                    );
+                }
+                "SP" => {
+                    let start = Game::from_diagram(&value.replace('/', "\n"))
+                        .map_err(SgfError::InvalidPosition)?;
+                    config = config.start_board(start.board);
+                }
+                "PR" => meta.p1_name = Some(value.clone()),
+                "PY" => meta.p2_name = Some(value.clone()),
+                _ => {}
+            }
+        }
+
+        if !connect_four {
+            return Err(SgfError::NotConnectFour);
+        }
+
+        let mut game = config.meta(meta).build().map_err(SgfError::Rejected)?;
+
+        for (index, node) in moves.iter().enumerate() {
+            let [(tag, value)] = &node[..] else {
+                return Err(SgfError::Malformed(format!(
+                    "move {} needs exactly one R or Y property",
+                    index + 1
+                )));
+            };
+            let player = match tag.as_str() {
+                "R" => Player::One,
+                "Y" => Player::Two,
+                _ => return Err(SgfError::InvalidValue(tag.clone(), value.clone())),
+            };
+
+            if player != game.current_player {
+                return Err(SgfError::WrongPlayer(index));
+            }
+
+            let number = value
+                .parse()
+                .map_err(|_| SgfError::InvalidValue(tag.clone(), value.clone()))?;
+            Column::from_user(number)
+                .and_then(|column| game.play_move(column))
+                .map_err(|err| SgfError::InvalidMove(index, err))?;
+        }
+
+        Ok(game)
+    }
+
+    fn to_diagram(&self) -> String {
+        self.diagram_rows().join("\n")
+    }
+
+    // The board as plain text to paste into a chat or an issue: column
+    // numbers over the diagram, then whose turn it is. There are never any
+    // colour codes, whatever the terminal settings.
+    fn render_snapshot(&self) -> String {
+        let numbers: String = (1..=BOARD_WIDTH)
+            .map(|column| (column % 10).to_string())
+            .collect();
+        let status = match self.side_to_move() {
+            Player::None => format!("Game over, {}", self.result()),
+            player => format!(
+                "{} ({}) to move",
+                self.player_name(player),
+                match player {
+                    Player::One => 'X',
+                    _ => 'O',
+                }
+            ),
+        };
+
+        format!("{}\n{}\n{}\n", numbers, self.to_diagram(), status)
+    }
+
+    fn board_fingerprint(&self) -> String {
+        let rows = self.diagram_rows();
+
+        let to_move = match self.side_to_move() {
+            Player::One => 'X',
+            Player::Two => 'O',
+            Player::None => '-',
+        };
+
+        format!("{}:{}", rows.join("/"), to_move)
+    }
+
+    // Scores a turn-limited game by open threats, which still need a free
+    // square to become a four. Komi is added to Player 2's score to offset
+    // moving second.
+    fn score(&self, player: Player) -> f64 {
+        let threats = self.threat_squares(player).len() as f64;
+
+        match player {
+            Player::Two => threats + self.komi.unwrap_or(0.0),
+            _ => threats,
+        }
+    }
+
+    // Equal scores are a draw, unless komi is in play, in which case Player 2
+    // takes the tie.
+    fn scored_winner(&self) -> Player {
+        let one = self.score(Player::One);
+        let two = self.score(Player::Two);
+
+        if one > two {
+            Player::One
+        } else if two > one || self.komi.is_some() {
+            Player::Two
+        } else {
+            Player::None
+        }
+    }
+
+    // The most `player` could still score with `moves` of their own left,
+    // and whether they could connect outright instead. Only windows free of
+    // opponent discs can still become threats, and only if all but one of
+    // their gaps can be filled in time.
+    fn score_ceiling(&self, player: Player, moves: usize) -> (f64, bool) {
+        let target = player as u8;
+        let mut squares = Vec::new();
+        let mut can_connect = false;
+
+        for row in 0..BOARD_HEIGHT as isize {
+            for col in 0..BOARD_WIDTH as isize {
+                for (row_step, col_step) in self.win_directions.directions() {
+                    let length = self.win_length(row_step, col_step) as usize;
+                    let window: Vec<(usize, usize)> = (0..length as isize)
+                        .filter_map(|i| self.cell_at(row + row_step * i, col + col_step * i))
+                        .collect();
+
+                    if window.len() != length
+                        || window
+                            .iter()
+                            .any(|&(r, c)| self.board[r][c] != 0 && self.board[r][c] != target)
+                    {
+                        continue;
+                    }
+
+                    let empty: Vec<(usize, usize)> = window
+                        .into_iter()
+                        .filter(|&(r, c)| self.board[r][c] == 0)
+                        .collect();
+
+                    if empty.len() <= moves {
+                        can_connect = true;
+                    }
+                    if !empty.is_empty() && empty.len() - 1 <= moves {
+                        squares.extend(empty);
+                    }
+                }
+            }
+        }
+
+        squares.sort();
+        squares.dedup();
+
+        let komi = match player {
+            Player::Two => self.komi.unwrap_or(0.0),
+            _ => 0.0,
+        };
+
+        (squares.len() as f64 + komi, can_connect)
+    }
+
+    // Under the mercy rule a side leading by at least the margin wins once
+    // the lead can't be overcome before `limit`. Every disc still to come
+    // could fill one of the leader's threat squares, and the trailing side
+    // must have no way left to connect or to reach the leader's score.
+    fn mercy_winner(&self, limit: u8) -> Option<Player> {
+        let margin = self.mercy? as f64;
+        let left = limit.saturating_sub(self.current_move) as usize;
+
+        [Player::One, Player::Two].into_iter().find(|&leader| {
+            let trailing = match leader {
+                Player::One => Player::Two,
+                _ => Player::One,
+            };
+            let trailing_moves = if self.current_player == trailing {
+                left.div_ceil(2)
+            } else {
+                left / 2
+            };
+
+            let lead = self.score(leader) - self.score(trailing);
+            let floor = self.score(leader) - left as f64;
+            let (ceiling, can_connect) = self.score_ceiling(trailing, trailing_moves);
+
+            lead >= margin && !can_connect && floor > ceiling
+        })
+    }
+
+    fn longest_run(&self, player: Player) -> u8 {
+        let target = player as u8;
+        let mut longest = 0;
+
+        if player == Player::None {
+            return longest;
+        }
+
+        for row in 0..BOARD_HEIGHT {
+            for col in 0..BOARD_WIDTH {
+                if self.board[row][col] != target {
+                    continue;
+                }
+
+                for (row_step, col_step) in self.win_directions.directions() {
+                    longest = longest.max(self.run_length(row, col, row_step, col_step));
+                }
+            }
+        }
+
+        longest
+    }
+
+    // Encodes the board as a binary PPM image with each cell drawn as a
+    // coloured disc on a blue frame.
+    fn render_ppm(&self) -> Vec<u8> {
+        let view = self.board_view();
+        let width = view.width() * FRAME_CELL_SIZE;
+        let height = view.height() * FRAME_CELL_SIZE;
+        let mut image = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+
+        let center = FRAME_CELL_SIZE as isize / 2;
+        let radius = center - 4;
+
+        for y in 0..height {
+            for x in 0..width {
+                let dx = (x % FRAME_CELL_SIZE) as isize - center;
+                let dy = (y % FRAME_CELL_SIZE) as isize - center;
+
+                let color = if dx * dx + dy * dy > radius * radius {
+                    [0, 60, 170]
+                } else {
+                    match view.get(y / FRAME_CELL_SIZE, x / FRAME_CELL_SIZE) {
+                        Some(Player::One) => [220, 30, 30],
+                        Some(Player::Two) => [240, 210, 0],
+                        _ => [240, 240, 240],
+                    }
+                };
+
+                image.extend_from_slice(&color);
+            }
+        }
+
+        image
+    }
+
+    // Writes one frame per position of the game, from the starting position
+    // up to the current one, named so they sort in play order.
+    fn render_frames_to_dir(&self, dir: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+
+        for move_index in 0..=self.history.len() {
+            let frame = self.position_at(move_index).render_ppm();
+            std::fs::write(dir.join(format!("frame_{:03}.ppm", move_index)), frame)?;
+        }
+
+        Ok(())
+    }
+
+    // Grades every move from the mover's point of view: taking or missing an
+    // immediate win, handing the opponent one, or letting the static
+    // evaluation slip by `INACCURACY_MARGIN` or more.
+    fn annotate_move(&self, move_index: usize) -> String {
+        let before = self.position_at(move_index);
+        let after = self.position_at(move_index + 1);
+        let column = self.history[move_index];
+        let mover = before.current_player;
+        let wins = before.winning_moves(mover);
+
+        if wins.contains(&column) {
+            return "best, wins the game".to_string();
+        }
+
+        if let Some(win) = wins.first() {
+            return format!("blunder, missed the win in column {}", win.to_user());
+        }
+
+        let safe_alternative = before
+            .available_columns()
+            .iter()
+            .any(|&other| !before.is_losing_move(other));
+
+        if safe_alternative && before.is_losing_move(column) {
+            let replies = after.winning_moves(after.current_player);
+            return format!("blunder, allows a win in column {}", replies[0].to_user());
+        }
+
+        let sign = if mover == Player::One { 1 } else { -1 };
+        let (eval_before, eval_after) = (sign * before.evaluate(), sign * after.evaluate());
+
+        if eval_before - eval_after >= INACCURACY_MARGIN {
+            format!(
+                "inaccuracy, evaluation fell from {:+} to {:+}",
+                eval_before, eval_after
+            )
+        } else {
+            "good".to_string()
+        }
+    }
+
+    // Lists moves where the mover had an immediate win and didn't take it, or
+    // left the opponent's immediate win open while a block was possible.
+    fn strict_report(&self) -> Vec<String> {
+        let mut report = Vec::new();
+
+        for (move_index, &column) in self.history.iter().enumerate() {
+            let position = self.position_at(move_index);
+            let wins = position.winning_moves(position.current_player);
+            let threats = position.winning_moves(position.next_player());
+
+            if !wins.is_empty() && !wins.contains(&column) {
+                report.push(format!(
+                    "move {}: missed win at col {}",
+                    move_index + 1,
+                    wins[0].to_user()
+                ));
+            } else if wins.is_empty() && threats.len() == 1 && threats[0] != column {
+                report.push(format!(
+                    "move {}: failed to block col {}",
+                    move_index + 1,
+                    threats[0].to_user()
+                ));
+            }
+        }
+
+        report
+    }
+
+    fn annotate_game(&self) -> String {
+        let mut report = String::new();
+
+        for (move_index, column) in self.history.iter().enumerate() {
+            let player = match self.position_at(move_index).current_player {
+                Player::Two => "P2",
+                _ => "P1",
+            };
+
+            report.push_str(&format!(
+                "{}. {} column {}: {}\n",
+                move_index + 1,
+                player,
+                column.to_user(),
+                self.annotate_move(move_index)
+            ));
+        }
+
+        report
+    }
+
+    // Whether `player`, on turn, can force a line within `moves` of their own
+    // moves whatever the opponent replies.
+    fn forces_win(&self, player: Player, moves: u8) -> bool {
+        if moves == 0 || self.is_finished || self.current_player != player {
+            return false;
+        }
+
+        self.available_columns()
+            .into_iter()
+            .any(|column| self.move_forces_win(player, column, moves))
+    }
+
+    fn move_forces_win(&self, player: Player, column: Column, moves: u8) -> bool {
+        SEARCH_NODES.fetch_add(1, Ordering::Relaxed);
+        let mut after = self.clone();
+
+        if after.play_move(column).is_err() {
+            return false;
+        }
+        if after.is_finished {
+            return after.winner == player;
+        }
+
+        after
+            .move_iter()
+            .all(|(_, position)| position.forces_win(player, moves - 1))
+    }
+
+    // Rough puzzle difficulty for the side to move: ten points for each move
+    // the shortest forced win takes, plus one for every legal move that
+    // doesn't force it, since those are the ones a solver can be tempted by.
+    // Positions with no forced win in reach score past the search depth.
+    fn difficulty_score(&self) -> u32 {
+        let player = self.current_player;
+        let columns = self.available_columns();
+
+        for moves in 1..=FORCED_WIN_DEPTH {
+            let winning = columns
+                .iter()
+                .filter(|&&column| self.move_forces_win(player, column, moves))
+                .count();
+
+            if winning > 0 {
+                return 10 * moves as u32 + (columns.len() - winning) as u32;
+            }
+        }
+
+        10 * (FORCED_WIN_DEPTH as u32 + 1) + columns.len() as u32
+    }
+
+    // Extra moves the winner took over the fastest win they could force,
+    // from the first position where one was within `FORCED_WIN_DEPTH` of
+    // their moves. Negative when the opponent let them win sooner. There is
+    // no full solver, so wins forced from further out go unnoticed.
+    fn win_efficiency(&self) -> Option<i32> {
+        let winner = self.winner;
+
+        if winner == Player::None || !self.connects(winner) {
+            return None;
+        }
+
+        (0..self.history.len()).find_map(|move_index| {
+            let position = self.position_at(move_index);
+            let fastest =
+                (1..=FORCED_WIN_DEPTH).find(|&moves| position.forces_win(winner, moves))?;
+            let taken = (self.history.len() - move_index).div_ceil(2);
+
+            Some(taken as i32 - fastest as i32)
+        })
+    }
+
+    // Whether every move the side to move has lets the opponent force a
+    // win within the search depth, so the game is lost with best play.
+    fn is_lost(&self) -> bool {
+        let opponent = self.next_player();
+
+        !self.is_finished
+            && self
+                .move_iter()
+                .all(|(_, position)| position.forces_win(opponent, FORCED_WIN_DEPTH - 1))
+    }
+
+    // The static evaluation, except that a win either side can force scores
+    // as won already: within `FORCED_WIN_DEPTH` moves for the side on turn,
+    // or one less after any reply for the side that just moved. The
+    // shallower check still keeps a win found on the previous move.
+    fn searched_eval(&self) -> i32 {
+        let score = |player: Player| match player {
+            Player::One => WIN_SCORE,
+            _ => -WIN_SCORE,
+        };
+
+        if self.is_finished {
+            return self.evaluate();
+        }
+
+        let mover = self.current_player;
+        let opponent = self.next_player();
+
+        if self.forces_win(mover, FORCED_WIN_DEPTH) {
+            return score(mover);
+        }
+
+        if self.is_lost() {
+            score(opponent)
+        } else {
+            self.evaluate()
+        }
+    }
+
+    // The expected line of best play from here, up to `depth` moves or the
+    // end of the game. Each side takes its quickest forced win in reach, and
+    // otherwise the move `evaluator` likes best among those that don't let
+    // the opponent force one, preferring search order on ties.
+    pub fn principal_variation(&self, depth: u8, evaluator: &dyn Evaluator) -> Vec<Column> {
+        let mut position = self.clone();
+        let mut line = Vec::new();
+
+        while line.len() < depth as usize && !position.is_finished {
+            let player = position.current_player;
+            let moves = position.ordered_moves(player);
+
+            let forced = (1..=FORCED_WIN_DEPTH).find_map(|moves_ahead| {
+                moves
+                    .iter()
+                    .copied()
+                    .find(|&column| position.move_forces_win(player, column, moves_ahead))
+            });
+            let safe: Vec<Column> = moves
+                .iter()
+                .copied()
+                .filter(|&column| {
+                    let mut after = position.clone();
+                    after.play_move(column).is_ok()
+                        && !after.forces_win(after.current_player, FORCED_WIN_DEPTH - 1)
+                })
+                .collect();
+            let candidates = if safe.is_empty() { moves } else { safe };
+
+            let Some(column) = forced.or_else(|| {
+                candidates.into_iter().min_by_key(|&column| {
+                    let mut after = position.clone();
+                    after
+                        .play_move(column)
+                        .map_or(i32::MAX, |_| -evaluator.evaluate(&after, player))
+                })
+            }) else {
+                break;
+            };
+
+            position
+                .play_move(column)
+                .expect("principal variation only plays legal moves");
+            line.push(column);
+        }
+
+        line
+    }
+
+    // The searched evaluation of every position in the game, start included.
+    // Entries already in `recorded` are taken as they are.
+    fn searched_evals(&self, recorded: &[Option<i32>]) -> Vec<i32> {
+        (0..=self.history.len())
+            .map(|move_index| match recorded.get(move_index) {
+                Some(&Some(eval)) => eval,
+                _ => self.position_at(move_index).searched_eval(),
+            })
+            .collect()
+    }
+
+    // The first move that swung the evaluation by more than `threshold`
+    // against the side that played it, by index into the history.
+    fn first_blunder(&self, evals: &[i32], threshold: i32) -> Option<usize> {
+        key_moments(evals, threshold)
+            .into_iter()
+            .find(|&(move_index, swing)| {
+                let mover_sign = match self.position_at(move_index).current_player {
+                    Player::Two => -1,
+                    _ => 1,
+                };
+
+                swing.signum() != mover_sign
+            })
+            .map(|(move_index, _)| move_index)
+    }
+
+    // The key moments of the game with these per-position `evals`, as report
+    // lines. The biggest swing against the loser is the one that lost it.
+    fn describe_key_moments(&self, evals: &[i32], threshold: i32) -> Vec<String> {
+        let moments = key_moments(evals, threshold);
+        let winner_sign = match self.winner {
+            Player::One => 1,
+            Player::Two => -1,
+            Player::None => 0,
+        };
+        let losing = moments
+            .iter()
+            .filter(|&&(_, swing)| swing.signum() == winner_sign)
+            .filter(|&&(move_index, _)| self.position_at(move_index).current_player != self.winner)
+            .max_by_key(|&&(_, swing)| swing.abs())
+            .map(|&(move_index, _)| move_index);
+
+        let describe = |eval: i32| match eval {
+            WIN_SCORE => "a forced win for P1".to_string(),
+            eval if eval == -WIN_SCORE => "a forced win for P2".to_string(),
+            eval => format!("{:+}", eval),
+        };
+
+        moments
+            .into_iter()
+            .map(|(move_index, swing)| {
+                let before = evals[move_index];
+                let mover_sign = match self.position_at(move_index).current_player {
+                    Player::Two => -1,
+                    _ => 1,
+                };
+                let verdict = if Some(move_index) == losing {
+                    "was the losing blunder"
+                } else if swing.signum() == mover_sign {
+                    "turned the game"
+                } else {
+                    "was a blunder"
+                };
+
+                format!(
+                    "move {} {}: eval swung from {} to {}",
+                    move_index + 1,
+                    verdict,
+                    describe(before),
+                    describe(before + swing)
+                )
+            })
+            .collect()
+    }
+
+    // Walks the history and diffs each side's threat squares before and after
+    // every move. A square leaves the list only when it is filled, which is a
+    // block unless the threat's owner filled it themselves.
+    fn threat_timeline(&self) -> Vec<ThreatEvent> {
+        let mut events = Vec::new();
+        let mut previous = self.position_at(0);
+
+        for (move_index, &column) in self.history.iter().enumerate() {
+            let mut current = previous.clone();
+            current
+                .play_move(column)
+                .expect("history only contains legal moves");
+
+            for player in [Player::One, Player::Two] {
+                let before = previous.threat_squares(player);
+                let after = current.threat_squares(player);
+                let mut record = |(row, col): (usize, usize), change| {
+                    events.push(ThreatEvent {
+                        move_number: move_index + 1,
+                        player,
+                        row,
+                        col,
+                        change,
+                    })
+                };
+
+                for &square in after.iter().filter(|square| !before.contains(square)) {
+                    record(square, ThreatChange::Created);
+                }
+
+                for &square in before.iter().filter(|square| !after.contains(square)) {
+                    if previous.current_player == player {
+                        record(square, ThreatChange::Converted);
+                    } else {
+                        record(square, ThreatChange::Blocked);
+                    }
+                }
+            }
+
+            previous = current;
+        }
+
+        events
+    }
+
+    // Takes back the last move, keeping it on the redo stack until a new
+    // move is played.
+    pub fn undo(&mut self) -> Result<(), MoveError> {
+        let column = *self.history.last().ok_or(MoveError::NothingToUndo)?;
+        let mut redo = std::mem::take(&mut self.redo);
+        redo.push(column);
+
+        // A takeback drops a pending draw offer but not the cooldown.
+        *self = Game {
+            redo,
+            draw_offered_at: self.draw_offered_at,
+            ..self.position_at(self.history.len() - 1)
+        };
+
+        Ok(())
+    }
+
+    // Takes back `plies` moves together, or none if there aren't that many.
+    fn undo_turn(&mut self, plies: usize) -> Result<(), MoveError> {
+        if self.history.len() < plies {
+            return Err(MoveError::NothingToUndo);
+        }
+
+        for _ in 0..plies {
+            self.undo()?;
+        }
+
+        Ok(())
+    }
+
+    fn redo(&mut self) -> Result<(), MoveError> {
+        let mut redo = std::mem::take(&mut self.redo);
+        let column = redo.pop().ok_or(MoveError::NothingToRedo)?;
+
+        let result = self.play_move(column);
+        if result.is_err() {
+            redo.push(column);
+        }
+        self.redo = redo;
+
+        result
+    }
+
+    // Replays columns numbered from 1 on a new game. Unlike loading, the
+    // error says which move in the list was illegal.
+    fn verify_replay(moves: &[usize]) -> Result<Game, (usize, MoveError)> {
+        let mut game = Game::default();
+
+        for (index, &number) in moves.iter().enumerate() {
+            Column::from_user(number)
+                .and_then(|column| game.play_move(column))
+                .map_err(|err| (index, err))?;
+        }
+
+        Ok(game)
+    }
+
+    // Replays both games side by side and gives the first ply where their
+    // boards differ, with what set them apart. Games whose boards match all
+    // the way can still differ in length or in how they ended.
+    fn compare_replays(&self, other: &Game) -> Option<(usize, String)> {
+        let plies = self.history.len().max(other.history.len());
+
+        for ply in 0..=plies {
+            let ours = self.position_at(ply);
+            let theirs = other.position_at(ply);
+
+            if ours.board == theirs.board {
+                continue;
+            }
+
+            if ply == 0 {
+                return Some((ply, "the starting positions differ".to_string()));
+            }
+
+            let description = match (self.history.get(ply - 1), other.history.get(ply - 1)) {
+                (Some(a), Some(b)) if a != b => {
+                    format!("column {} against column {}", a.to_user(), b.to_user())
+                }
+                (Some(a), None) => {
+                    format!("column {} against the end of the second game", a.to_user())
+                }
+                (None, Some(b)) => {
+                    format!("the end of the first game against column {}", b.to_user())
+                }
+                _ => "the same column landing differently".to_string(),
+            };
+
+            return Some((ply, description));
+        }
+
+        if self.result() != other.result() {
+            return Some((
+                plies,
+                format!(
+                    "the results differ, {} against {}",
+                    self.result(),
+                    other.result()
+                ),
+            ));
+        }
+
+        None
+    }
+
+    fn branch(&self) -> Game {
+        self.clone()
+    }
+
+    fn position_at(&self, move_index: usize) -> Game {
+        let mut game =
+            Game::from_board(self.start_board).expect("start board was validated on creation");
+        game.rules = self.rules.clone();
+        game.win_directions = self.win_directions;
+        game.win_length_orthogonal = self.win_length_orthogonal;
+        game.win_length_diagonal = self.win_length_diagonal;
+        game.exact_length = self.exact_length;
+        game.wrap = self.wrap;
+        game.center_gravity = self.center_gravity;
+        game.bounce = self.bounce;
+        game.fill_order = self.fill_order.clone();
+        // The start position was judged with the classic lengths.
+        game.is_finished = false;
+        game.winner = game.calculate_winner();
+        game.threats = game.count_threats();
+        game.turn_limit = self.turn_limit;
+        game.komi = self.komi;
+        game.mercy = self.mercy;
+        game.display = self.display;
+        game.draw_cooldown = self.draw_cooldown;
+        game.clock = self.clock;
+        game.meta = self.meta.clone();
+
+        for &column in self.history.iter().take(move_index) {
+            game.play_move(column)
+                .expect("history only contains legal moves");
+        }
+
+        // Replaying retimes every move, so the original times are carried
+        // over by move number, which follows discs that tilting moved.
+        game.started = self.started;
+        game.fill_times = [[None; BOARD_WIDTH]; BOARD_HEIGHT];
+        for (row, numbers) in game.move_numbers.iter().enumerate() {
+            for (col, &number) in numbers.iter().enumerate() {
+                if number > 0 {
+                    game.fill_times[row][col] = self.fill_time_of(number);
+                }
+            }
+        }
+
+        game
+    }
+
+    // When each cell was filled, measured from the start of the game. Discs
+    // from the starting position or replayed from a save have no time.
+    fn fill_times(&self) -> &FillTimes {
+        &self.fill_times
+    }
+
+    fn fill_time_of(&self, number: u8) -> Option<Duration> {
+        self.numbered_cells()
+            .find(|&(_, _, cell_number)| cell_number == number)
+            .and_then(|(row, col, _)| self.fill_times[row][col])
+    }
+
+    fn numbered_cells(&self) -> impl Iterator<Item = (usize, usize, u8)> + '_ {
+        self.move_numbers
+            .iter()
+            .enumerate()
+            .flat_map(|(row, numbers)| {
+                numbers
+                    .iter()
+                    .enumerate()
+                    .map(move |(col, &number)| (row, col, number))
+            })
+    }
+
+    // Time spent on each cell's move: its fill time less the previous
+    // move's, counting from the start when the previous move wasn't timed.
+    fn think_times(&self) -> FillTimes {
+        let mut think = [[None; BOARD_WIDTH]; BOARD_HEIGHT];
+
+        for (row, col, number) in self.numbered_cells() {
+            let Some(filled) = self.fill_times()[row][col] else {
+                continue;
+            };
+            let previous = self.fill_time_of(number - 1).unwrap_or(Duration::ZERO);
+
+            think[row][col] = Some(filled.saturating_sub(previous));
+        }
+
+        think
+    }
+
+    fn render_think_map(&self) -> String {
+        let mut lines = vec!["Seconds spent on each move:".to_string()];
+
+        for row in self.think_times() {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|time| match time {
+                    Some(time) => format!("{:>5.1}", time.as_secs_f64()),
+                    None => "    .".to_string(),
+                })
+                .collect();
+            lines.push(cells.join(""));
+        }
+
+        let mut map = lines.join("\n");
+        map.push('\n');
+        map
+    }
+
+    // A move loses immediately when it hands the opponent a winning reply.
+    fn is_losing_move(&self, column: Column) -> bool {
+        let mut game = self.clone();
+
+        game.play_move(column).is_ok()
+            && !game.is_finished
+            && !game.winning_moves(game.current_player).is_empty()
+    }
+
+    // Under --safe a losing move is turned down, unless every move loses and
+    // turning them all down would leave nothing to play.
+    fn refuses_move(&self, column: Column) -> bool {
+        self.is_losing_move(column)
+            && self
+                .available_columns()
+                .iter()
+                .any(|&other| !self.is_losing_move(other))
+    }
+
+    // The one column left to play, if only one is.
+    fn forced_column(&self) -> Option<Column> {
+        match self.available_columns()[..] {
+            [column] => Some(column),
+            _ => None,
+        }
+    }
+
+    pub fn available_columns(&self) -> Vec<Column> {
+        let view = self.board_view();
+
+        (0..view.width())
+            .filter(|&column| view.column_height(column) < view.height())
+            .map(Column)
+            .collect()
+    }
+
+    // Where `column` comes in the fill order. Columns left out of it go last.
+    fn fill_rank(&self, column: Column) -> usize {
+        self.fill_order
+            .iter()
+            .position(|&other| other == column)
+            .unwrap_or(BOARD_WIDTH)
+    }
+
+    // Legal columns in the order a search should try them: wins for `player`
+    // first, then blocks against the opponent, then the rest in fill order.
+    fn ordered_moves(&self, player: Player) -> Vec<Column> {
+        let opponent = match player {
+            Player::One => Player::Two,
+            _ => Player::One,
+        };
+        let mut rest = self.available_columns();
+        rest.sort_by_key(|&column| self.fill_rank(column));
+
+        let mut moves = self.winning_moves(player);
+        for column in self.winning_moves(opponent).into_iter().chain(rest) {
+            if !moves.contains(&column) {
+                moves.push(column);
+            }
+        }
+
+        moves
+    }
+
+    // Columns the side to move has to play to stop an immediate loss. Empty
+    // when they can win on the spot instead.
+    fn forced_blocks(&self) -> Vec<Column> {
+        if !self.winning_moves(self.current_player).is_empty() {
+            return Vec::new();
+        }
+
+        self.winning_moves(self.next_player())
+    }
+
+    // The block `column` walks past, if there is exactly one to make and it
+    // hasn't been flagged already.
+    fn missed_block(&self, column: Column, warned: &[Column]) -> Option<Column> {
+        match self.forced_blocks()[..] {
+            [block] if block != column && !warned.contains(&block) => Some(block),
+            _ => None,
+        }
+    }
+
+    pub fn outcome(&self) -> GameOutcome {
+        match (self.is_finished, self.winner) {
+            (false, _) => GameOutcome::Ongoing,
+            (true, Player::None) => GameOutcome::Draw,
+            (true, winner) => GameOutcome::Win(winner),
+        }
+    }
+
+    // Every position one legal move away, paired with the column played, for
+    // searches built on the engine. Columns come in the fill order, centre
+    // first by default since those tend to be the strongest replies, and a
+    // finished game has none.
+    fn move_iter(&self) -> impl Iterator<Item = (Column, Game)> {
+        let mut columns = if self.is_finished {
+            Vec::new()
+        } else {
+            self.available_columns()
+        };
+        columns.sort_by_key(|&column| self.fill_rank(column));
+
+        let game = self.clone();
+        columns.into_iter().filter_map(move |column| {
+            let mut child = game.clone();
+            child.play_move(column).ok()?;
+            Some((column, child))
+        })
+    }
+
+    // What each legal move would lead to, worked out on a copy of the game.
+    pub fn legal_moves_with_outcomes(&self) -> Vec<(Column, GameOutcome)> {
+        if self.is_finished {
+            return Vec::new();
+        }
+
+        self.available_columns()
+            .into_iter()
+            .filter_map(|column| {
+                let mut game = self.clone();
+                game.play_move(column).ok()?;
+                Some((column, game.outcome()))
+            })
+            .collect()
+    }
+
+    pub fn winning_moves(&self, player: Player) -> Vec<Column> {
+        let mut game = self.clone();
+        game.current_player = player;
+
+        game.legal_moves_with_outcomes()
+            .into_iter()
+            .filter(|&(_, outcome)| outcome == GameOutcome::Win(player))
+            .map(|(column, _)| column)
+            .collect()
+    }
+
+    // Layout: magic, version, width, height, the starting position packed
+    // four cells per byte, then the history. Version 1 stores the move count
+    // (u16 LE) and one byte per move, version 2 the `encode_moves` packing,
+    // and version 3 follows that with a u16 LE length and the header text.
+    fn save_binary(&self, w: &mut impl Write) -> io::Result<()> {
+        let mut cells = [0u8; (BOARD_WIDTH * BOARD_HEIGHT).div_ceil(4)];
+
+        for (i, &cell) in self.start_board.iter().flatten().enumerate() {
+            cells[i / 4] |= cell << ((i % 4) * 2);
+        }
+
+        let mut meta = self.meta.clone();
+        meta.date.get_or_insert_with(today);
+        meta.result = Some(self.result().to_string());
+        let header = meta.to_header();
+        let header_length = u16::try_from(header.len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "the save header is {} bytes, over the 65535 allowed",
+                    header.len()
+                ),
+            )
+        })?;
+
+        w.write_all(SAVE_MAGIC)?;
+        w.write_all(&[SAVE_VERSION, BOARD_WIDTH as u8, BOARD_HEIGHT as u8])?;
+        w.write_all(&cells)?;
+        w.write_all(&self.encode_moves())?;
+        w.write_all(&header_length.to_le_bytes())?;
+        w.write_all(header.as_bytes())
+    }
+
+    fn meta(&self) -> &GameMeta {
+        &self.meta
+    }
+
+    fn result(&self) -> &'static str {
+        match self.outcome() {
+            GameOutcome::Ongoing => "*",
+            GameOutcome::Win(Player::One) => "1-0",
+            GameOutcome::Win(_) => "0-1",
+            GameOutcome::Draw => "1/2-1/2",
+        }
+    }
+
+    fn load_binary(r: &mut impl Read) -> Result<Game, LoadError> {
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+
+        if &magic != SAVE_MAGIC {
+            return Err(LoadError::BadMagic);
+        }
+
+        let mut version = [0u8; 1];
+        r.read_exact(&mut version)?;
+
+        if !(1..=SAVE_VERSION).contains(&version[0]) {
+            return Err(LoadError::UnsupportedVersion(version[0]));
+        }
+
+        let mut dimensions = [0u8; 2];
+        r.read_exact(&mut dimensions)?;
+
+        let [width, height] = dimensions;
+
+        if width as usize != BOARD_WIDTH || height as usize != BOARD_HEIGHT {
+            return Err(LoadError::WrongDimensions(width, height));
+        }
+
+        let mut cells = [0u8; (BOARD_WIDTH * BOARD_HEIGHT).div_ceil(4)];
+        r.read_exact(&mut cells)?;
+
+        let mut board: Board = [[0; BOARD_WIDTH]; BOARD_HEIGHT];
+
+        for (i, cell) in board.iter_mut().flatten().enumerate() {
+            *cell = (cells[i / 4] >> ((i % 4) * 2)) & 0b11;
+
+            if *cell > Player::Two as u8 {
+                return Err(LoadError::InvalidCell(*cell));
+            }
+        }
+
+        let mut count = [0u8; 2];
+        r.read_exact(&mut count)?;
+
+        let count = u16::from_le_bytes(count) as usize;
+        let moves = if version[0] == 1 {
+            let mut moves = vec![0u8; count];
+            r.read_exact(&mut moves)?;
+            moves
+                .iter()
+                .map(|&column| Column(column as usize))
+                .collect()
+        } else {
+            let mut encoded = vec![0u8; 2 + (count * MOVE_BITS).div_ceil(8)];
+            encoded[..2].copy_from_slice(&(count as u16).to_le_bytes());
+            r.read_exact(&mut encoded[2..])?;
+            Game::decode_moves(&encoded).map_err(LoadError::InvalidEncoding)?
+        };
+
+        let meta = if version[0] >= 3 {
+            let mut length = [0u8; 2];
+            r.read_exact(&mut length)?;
+
+            let mut header = vec![0u8; u16::from_le_bytes(length) as usize];
+            r.read_exact(&mut header)?;
+            GameMeta::from_header(&String::from_utf8_lossy(&header))?
+        } else {
+            GameMeta::default()
+        };
+
+        let mut game = Game::from_board(board).map_err(LoadError::InvalidPosition)?;
+        game.meta = meta;
+
+        for (index, &column) in moves.iter().enumerate() {
+            game.play_move(column)
+                .map_err(|err| LoadError::InvalidMove(index, err))?;
+        }
+
+        Ok(game)
+    }
+
+    // Packs the history as a u16 LE move count followed by 3 bits per move,
+    // least significant bit first.
+    fn encode_moves(&self) -> Vec<u8> {
+        let mut bytes = (self.history.len() as u16).to_le_bytes().to_vec();
+        bytes.resize(2 + (self.history.len() * MOVE_BITS).div_ceil(8), 0);
+
+        for (index, column) in self.history.iter().enumerate() {
+            for bit in 0..MOVE_BITS {
+                if column.0 & (1 << bit) != 0 {
+                    let position = index * MOVE_BITS + bit;
+                    bytes[2 + position / 8] |= 1 << (position % 8);
+                }
+            }
+        }
+
+        bytes
+    }
+
+    fn decode_moves(bytes: &[u8]) -> Result<Vec<Column>, DecodeError> {
+        if bytes.len() < 2 {
+            return Err(DecodeError::Truncated);
+        }
+
+        let count = u16::from_le_bytes([bytes[0], bytes[1]]) as usize;
+        let expected = 2 + (count * MOVE_BITS).div_ceil(8);
+
+        if bytes.len() < expected {
+            return Err(DecodeError::Truncated);
+        } else if bytes.len() > expected {
+            return Err(DecodeError::TrailingBytes);
+        }
+
+        (0..count)
+            .map(|index| {
+                let column = (0..MOVE_BITS).fold(0, |column, bit| {
+                    let position = index * MOVE_BITS + bit;
+                    let set = bytes[2 + position / 8] & (1 << (position % 8)) != 0;
+                    column | (set as usize) << bit
+                });
+
+                if column < BOARD_WIDTH {
+                    Ok(Column(column))
+                } else {
+                    Err(DecodeError::InvalidColumn(index, column))
+                }
+            })
+            .collect()
+    }
+
+    // Perft-style count of the positions reached after `depth` plies, where a
+    // finished game counts as a leaf even if it ends earlier.
+    fn count_leaves(&self, depth: u8) -> u64 {
+        if depth == 0 || self.is_finished {
+            return 1;
+        }
+
+        self.available_columns()
+            .into_iter()
+            .map(|column| {
+                let mut child = self.clone();
+                child
+                    .play_move(column)
+                    .expect("available column is playable");
+                child.count_leaves(depth - 1)
+            })
+            .sum()
+    }
+
+    pub fn side_to_move(&self) -> Player {
+        if self.is_finished {
+            Player::None
+        } else {
+            self.current_player
+        }
+    }
+
+    // The cell the last disc landed in, as row and column.
+    fn last_landing(&self) -> Option<(usize, usize)> {
+        let &column = self.history.last()?;
+        let row =
+            (0..BOARD_HEIGHT).find(|&row| self.move_numbers[row][column.0] == self.current_move)?;
+
+        Some((row, column.0))
+    }
+
+    // The player who made the last move, read back from the state
+    // `play_move` leaves: it only rotates `current_player` when nobody won.
+    fn last_mover(&self) -> Player {
+        if self.winner != Player::None {
+            self.current_player
+        } else {
+            self.next_player()
+        }
+    }
+
+    // Works the outcome out again after a rules variant has changed the board
+    // behind `play_move`'s back, then hands the turn on as usual.
+    fn rejudge(&mut self, mover: Player) {
+        self.is_finished = false;
+        self.winner = self.calculate_winner();
+        // Discs may have moved anywhere, so the threats are counted afresh.
+        self.threats = self.count_threats();
+        self.current_player = mover;
+
+        if self.winner == Player::None {
+            self.current_player = self.next_player();
+        }
+    }
+
+    fn forfeit(&mut self) {
+        self.winner = self.next_player();
+        self.is_finished = true;
+    }
+
+    // The side to move offers a draw and still plays their move; the
+    // opponent answers before theirs. Each player waits `draw_cooldown`
+    // moves between offers, so they can't be made every turn.
+    fn offer_draw(&mut self) -> Result<(), MoveError> {
+        if self.is_finished {
+            return Err(MoveError::GameFinished);
+        }
+
+        if self.draw_offer.is_some() {
+            return Err(MoveError::DrawAlreadyOffered);
+        }
+
+        let seat = match self.current_player {
+            Player::Two => 1,
+            _ => 0,
+        };
+
+        if let Some(offered_at) = self.draw_offered_at[seat] {
+            let ready_at = offered_at.saturating_add(self.draw_cooldown);
+
+            if self.current_move < ready_at {
+                return Err(MoveError::DrawOfferCooldown {
+                    moves: ready_at - self.current_move,
+                });
+            }
+        }
+
+        self.draw_offer = Some(self.current_player);
+        self.draw_offered_at[seat] = Some(self.current_move);
+        Ok(())
+    }
+
+    fn draw_offered(&self) -> bool {
+        self.draw_offer
+            .is_some_and(|offerer| offerer != self.current_player)
+    }
+
+    fn accept_draw(&mut self) -> Result<(), MoveError> {
+        if self.is_finished {
+            return Err(MoveError::GameFinished);
+        }
+
+        if !self.draw_offered() {
+            return Err(MoveError::NoDrawOffer);
+        }
+
+        self.draw_offer = None;
+        self.winner = Player::None;
+        self.is_finished = true;
+        Ok(())
+    }
+
+    fn decline_draw(&mut self) -> Result<(), MoveError> {
+        if !self.draw_offered() {
+            return Err(MoveError::NoDrawOffer);
+        }
+
+        self.draw_offer = None;
+        Ok(())
+    }
+
+    fn next_player(&self) -> Player {
+        match self.current_player {
+            Player::One => Player::Two,
+            _ => Player::One,
+        }
+    }
+
+    // Under --bounce a disc dropped into a full column rolls into the
+    // neighbour on the preferred side, or failing that the other one. Every
+    // other drop lands where it was aimed.
+    fn bounce_landing(&self, column: Column) -> Result<Column, MoveError> {
+        let Some(bounce) = self.bounce else {
+            return Ok(column);
+        };
+
+        if self.is_finished
+            || column.0 >= self.board_view().width()
+            || self.preview(column).is_some()
+        {
+            return Ok(column);
+        }
+
+        let steps = match bounce {
+            Bounce::Left => [-1, 1],
+            Bounce::Right => [1, -1],
+        };
+
+        steps
+            .into_iter()
+            .filter_map(|step| column.0.checked_add_signed(step).map(Column))
+            .find(|&neighbour| self.preview(neighbour).is_some())
+            .ok_or(MoveError::NoBounce)
+    }
+
+    fn preview(&self, column: Column) -> Option<usize> {
+        let view = self.board_view();
+
+        if column.0 >= view.width() {
+            return None;
+        }
+
+        if self.center_gravity {
+            return center_rows()
+                .into_iter()
+                .find(|&row| self.board[row][column.0] == 0);
+        }
+
+        let height = view.column_height(column.0);
+        (height < view.height()).then(|| view.height() - 1 - height)
+    }
+
+    pub fn play_move(&mut self, column: Column) -> Result<(), MoveError> {
+        self.rules.validate(self, column)?;
+        let column = self.bounce_landing(column)?;
+
+        if let Some(row) = self.preview(column) {
+            self.mark_threats(row, column.0, false);
+            self.board[row][column.0] = self.current_player as u8;
+            self.mark_threats(row, column.0, true);
+            self.current_move += 1;
+            self.move_numbers[row][column.0] = self.current_move;
+            self.fill_times[row][column.0] = Some(self.started.elapsed());
+            self.history.push(column);
+            self.redo.clear();
+        } else {
+            return Err(MoveError::ColumnFull);
+        }
+
+        let calculated_winner = self.calculate_winner();
+
+        if calculated_winner != Player::None {
+            self.winner = calculated_winner;
+        } else {
+            self.current_player = self.next_player();
+        }
+
+        self.apply_rules();
+
+        Ok(())
+    }
+
+    // Everything after the disc is down and the board judged: the rules
+    // variant has its say, then the turn limit and the mercy rule.
+    fn apply_rules(&mut self) {
+        let mut rules = std::mem::replace(&mut self.rules, Box::new(StandardRules));
+        rules.post_apply(self);
+        self.rules = rules;
+
+        // A four on the last allowed move still counts as a win, so the
+        // limit only decides games that are still open.
+        if let Some(limit) = self.turn_limit {
+            if !self.is_finished && self.current_move >= limit {
+                self.is_finished = true;
+                self.winner = self.scored_winner();
+            } else if !self.is_finished {
+                if let Some(leader) = self.mercy_winner(limit) {
+                    self.is_finished = true;
+                    self.winner = leader;
+                }
+            }
+        }
+    }
+
+    // Ends a game in which nothing can be played, judging the board as it
+    // stands the way the last move to fill it would have been judged.
+    fn settle(&mut self) {
+        self.winner = self.calculate_winner();
+        self.is_finished = true;
+        self.apply_rules();
+    }
+}
+
+// Collects everything that shapes a new game and validates it in one place.
+// The starting position is a diagram, a raw board or the empty board, and
+// any moves are replayed under the configured rules.
+#[derive(Default)]
+pub struct GameConfigBuilder {
+    diagram: Option<&'static str>,
+    start_board: Option<Board>,
+    moves: Vec<Column>,
+    handicap: Vec<Column>,
+    tiebreak: Tiebreak,
+    tilt: Option<Tilt>,
+    chaos: bool,
+    win_directions: WinDirections,
+    win_lengths: Option<(u8, u8)>,
+    exact_length: bool,
+    wrap: bool,
+    center_gravity: bool,
+    bounce: Option<Bounce>,
+    fill_order: Option<Vec<Column>>,
+    turn_limit: Option<u8>,
+    komi: Option<f64>,
+    mercy: Option<u8>,
+    display: DisplayOptions,
+    draw_cooldown: Option<u8>,
+    clock: Option<Clock>,
+    meta: GameMeta,
+}
+
+impl GameConfigBuilder {
+    pub fn new() -> GameConfigBuilder {
+        GameConfigBuilder::default()
+    }
+
+    fn diagram(mut self, diagram: &'static str) -> GameConfigBuilder {
+        self.diagram = Some(diagram);
+        self
+    }
+
+    fn start_board(mut self, board: Board) -> GameConfigBuilder {
+        self.start_board = Some(board);
+        self
+    }
+
+    pub fn moves(mut self, moves: Vec<Column>) -> GameConfigBuilder {
+        self.moves = moves;
+        self
+    }
+
+    fn handicap(mut self, handicap: Vec<Column>) -> GameConfigBuilder {
+        self.handicap = handicap;
+        self
+    }
+
+    fn tiebreak(mut self, tiebreak: Tiebreak) -> GameConfigBuilder {
+        self.tiebreak = tiebreak;
+        self
+    }
+
+    fn tilt(mut self, tilt: Option<Tilt>) -> GameConfigBuilder {
+        self.tilt = tilt;
+        self
+    }
+
+    fn chaos(mut self, chaos: bool) -> GameConfigBuilder {
+        self.chaos = chaos;
+        self
+    }
+
+    fn win_directions(mut self, win_directions: WinDirections) -> GameConfigBuilder {
+        self.win_directions = win_directions;
+        self
+    }
+
+    fn win_lengths(mut self, orthogonal: u8, diagonal: u8) -> GameConfigBuilder {
+        self.win_lengths = Some((orthogonal, diagonal));
+        self
+    }
+
+    fn exact_length(mut self, exact_length: bool) -> GameConfigBuilder {
+        self.exact_length = exact_length;
+        self
+    }
+
+    fn center_gravity(mut self, center_gravity: bool) -> GameConfigBuilder {
+        self.center_gravity = center_gravity;
+        self
+    }
+
+    fn wrap(mut self, wrap: bool) -> GameConfigBuilder {
+        self.wrap = wrap;
+        self
+    }
+
+    fn bounce(mut self, bounce: Option<Bounce>) -> GameConfigBuilder {
+        self.bounce = bounce;
+        self
+    }
+
+    fn fill_order(mut self, fill_order: Option<Vec<Column>>) -> GameConfigBuilder {
+        self.fill_order = fill_order;
+        self
+    }
+
+    fn turn_limit(mut self, turn_limit: Option<u8>) -> GameConfigBuilder {
+        self.turn_limit = turn_limit;
+        self
+    }
+
+    fn komi(mut self, komi: Option<f64>) -> GameConfigBuilder {
+        self.komi = komi;
+        self
+    }
+
+    fn mercy(mut self, mercy: Option<u8>) -> GameConfigBuilder {
+        self.mercy = mercy;
+        self
+    }
+
+    fn display(mut self, display: DisplayOptions) -> GameConfigBuilder {
+        self.display = display;
+        self
+    }
+
+    fn draw_cooldown(mut self, draw_cooldown: Option<u8>) -> GameConfigBuilder {
+        self.draw_cooldown = draw_cooldown;
+        self
+    }
+
+    fn clock(mut self, clock: Option<Clock>) -> GameConfigBuilder {
+        self.clock = clock;
+        self
+    }
+
+    fn meta(mut self, meta: GameMeta) -> GameConfigBuilder {
+        self.meta = meta;
+        self
+    }
+
+    pub fn build(self) -> Result<Game, ConfigError> {
+        if self.win_directions.directions().is_empty() {
+            return Err(ConfigError::NoWinDirections);
+        }
+
+        let (orthogonal, diagonal) = self.win_lengths.unwrap_or((WIN_LENGTH, WIN_LENGTH));
+        if !(2..=BOARD_WIDTH as u8).contains(&orthogonal) {
+            return Err(ConfigError::InvalidWinLength(orthogonal));
+        }
+        if !(2..=BOARD_HEIGHT as u8).contains(&diagonal) {
+            return Err(ConfigError::InvalidWinLength(diagonal));
+        }
+
+        if !self.handicap.is_empty() && (self.diagram.is_some() || self.start_board.is_some()) {
+            return Err(ConfigError::HandicapNeedsEmptyBoard);
+        }
+
+        // Tilting settles discs towards the bottom, undoing centre gravity.
+        if self.center_gravity && self.tilt.is_some() {
+            return Err(ConfigError::CenterGravityWithTilt);
+        }
+
+        // The mercy rule bounds scores by where discs are, so they must stay put.
+        if self.mercy.is_some() && self.turn_limit.is_none() {
+            return Err(ConfigError::MercyWithoutTurnLimit);
+        }
+        if self.mercy.is_some() && (self.tilt.is_some() || self.chaos) {
+            return Err(ConfigError::MercyWithMovingDiscs);
+        }
+
+        let mut game = match (self.diagram, self.start_board) {
+            (Some(diagram), _) => Game::from_diagram(diagram),
+            (None, Some(board)) => Game::from_board(board),
+            (None, None) if self.handicap.is_empty() => Ok(Game::default()),
+            (None, None) => Game::from_board(handicap_board(&self.handicap)?),
+        }
+        .map_err(ConfigError::InvalidPosition)?;
+
+        game.rules = match self.tiebreak {
+            Tiebreak::Draw => Box::new(StandardRules),
+            Tiebreak::LongestRun => Box::new(LongestRunRules),
+        };
+        if let Some(tilt) = self.tilt {
+            game.rules = Box::new(TiltRules {
+                tilt,
+                inner: game.rules,
+            });
+        }
+        if self.chaos {
+            game.rules = Box::new(ChaosRules { inner: game.rules });
+        }
+        game.win_directions = self.win_directions;
+        game.win_length_orthogonal = orthogonal;
+        game.win_length_diagonal = diagonal;
+        game.exact_length = self.exact_length;
+        game.wrap = self.wrap;
+        game.center_gravity = self.center_gravity;
+        game.bounce = self.bounce;
+        if let Some(fill_order) = self.fill_order {
+            game.fill_order = fill_order;
+        }
+        // The start position was judged with the classic lengths.
+        game.is_finished = false;
+        game.winner = game.calculate_winner();
+        game.threats = game.count_threats();
+        if !self.handicap.is_empty() && game.winner != Player::None {
+            return Err(ConfigError::WinningHandicap);
+        }
+        game.turn_limit = self.turn_limit;
+        game.komi = self.komi;
+        game.mercy = self.mercy;
+        game.display = self.display;
+        game.draw_cooldown = self.draw_cooldown.unwrap_or(DRAW_OFFER_COOLDOWN);
+        game.clock = self.clock;
+        game.meta = self.meta;
+
+        for (index, column) in self.moves.into_iter().enumerate() {
+            game.play_move(column)
+                .map_err(|err| ConfigError::InvalidMove(index, err))?;
+        }
+
+        // Replayed moves weren't played now, so they get no fill time.
+        game.started = Instant::now();
+        game.fill_times = [[None; BOARD_WIDTH]; BOARD_HEIGHT];
+
+        Ok(game)
+    }
+}
+
+// Drops Player 1's handicap discs into an empty board in order. They aren't
+// moves, so they get no move numbers and Player 2 moves first.
+fn handicap_board(columns: &[Column]) -> Result<Board, ConfigError> {
+    let mut board = [[0; BOARD_WIDTH]; BOARD_HEIGHT];
+
+    for (index, column) in columns.iter().enumerate() {
+        let row = (0..BOARD_HEIGHT)
+            .rev()
+            .find(|&row| board[row][column.0] == 0)
+            .ok_or(ConfigError::InvalidHandicap(index, MoveError::ColumnFull))?;
+
+        board[row][column.0] = Player::One as u8;
+    }
+
+    Ok(board)
+}
+
+#[derive(Default)]
+struct Options {
+    challenge: Option<&'static str>,
+    scripts: [Option<String>; 2],
+    names: [Option<String>; 2],
+    note: Option<String>,
+    load: Option<String>,
+    save: Option<String>,
+    auto_forced: bool,
+    tiebreak: Tiebreak,
+    tilt: Option<Tilt>,
+    chaos: bool,
+    win_length_orthogonal: Option<u8>,
+    win_length_diagonal: Option<u8>,
+    exact_length: bool,
+    wrap: bool,
+    center_gravity: bool,
+    bounce: Option<Bounce>,
+    fill_order: Option<Vec<Column>>,
+    turn_limit: Option<u8>,
+    komi: Option<f64>,
+    mercy: Option<u8>,
+    display: DisplayOptions,
+    draw_cooldown: Option<u8>,
+    clock: Option<Clock>,
+    colour: Option<bool>,
+    animate: bool,
+    autosave: bool,
+    resume: bool,
+    perft: Option<u8>,
+    coach: bool,
+    analyze: bool,
+    win_directions: WinDirections,
+    export_frames: Option<String>,
+    export_sgf: Option<String>,
+    safe: bool,
+    annotate: bool,
+    drill: bool,
+    lines: Option<String>,
+    warn_blocks: bool,
+    warn_forced_loss: bool,
+    timeline: bool,
+    strict_report: bool,
+    bench_replay: Option<String>,
+    diff_games: Option<(String, String)>,
+    max_retries: Option<u32>,
+    tt_size: Option<usize>,
+    retry_limit: RetryLimit,
+    confirm: bool,
+    auto_restart: Option<u64>,
+    win_pause: Option<Duration>,
+    rated: bool,
+    swap2: bool,
+    think_map: bool,
+    tui: bool,
+    column_lock: bool,
+    stats: bool,
+    handicap: Vec<Column>,
+    protocol: bool,
+    verbosity: Verbosity,
+    postgame_analysis: bool,
+    train: Option<TrainPattern>,
+    daily: bool,
+    mode: GameMode,
+    verify_log: Option<String>,
+    check: bool,
+    start_board: Option<Board>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--challenge" => {
+                    let name = args.next().ok_or("--challenge needs a puzzle name")?;
+                    let (_, diagram) = CHALLENGES
+                        .iter()
+                        .find(|(challenge, _)| *challenge == name)
+                        .ok_or_else(|| {
+                            let names: Vec<String> = challenges_by_difficulty()
+                                .iter()
+                                .map(|(challenge, score)| format!("{} ({})", challenge, score))
+                                .collect();
+                            format!(
+                                "unknown challenge '{}' (try, easiest first: {})",
+                                name,
+                                names.join(", ")
+                            )
+                        })?;
+
+                    options.challenge = Some(diagram);
+                }
+                flag @ ("--p1-script" | "--p2-script") => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| format!("{} needs a file path", flag))?;
+                    let index = if flag == "--p1-script" { 0 } else { 1 };
+
+                    options.scripts[index] = Some(path);
+                }
+                flag @ ("--p1-name" | "--p2-name") => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| format!("{} needs a name", flag))?;
+                    let index = if flag == "--p1-name" { 0 } else { 1 };
+
+                    options.names[index] = Some(name);
+                }
+                "--note" => {
+                    options.note = Some(args.next().ok_or("--note needs some text")?);
+                }
+                "--lines" => {
+                    options.lines = Some(args.next().ok_or("--lines needs a file path")?);
+                }
+                "--load" => {
+                    options.load = Some(args.next().ok_or("--load needs a file path")?);
+                }
+                "--save" => {
+                    options.save = Some(args.next().ok_or("--save needs a file path")?);
+                }
+                "--perft" => {
+                    let depth = args.next().ok_or("--perft needs a depth")?;
+                    let depth = depth
+                        .parse()
+                        .map_err(|err| format!("invalid perft depth '{}': {}", depth, err))?;
+
+                    options.perft = Some(depth);
+                }
+                "--max-retries" => {
+                    let count = args.next().ok_or("--max-retries needs a count")?;
+                    let count = count
+                        .parse()
+                        .map_err(|err| format!("invalid retry count '{}': {}", count, err))?;
+
+                    options.max_retries = Some(count);
+                }
+                "--tt-size" => {
+                    let size = args.next().ok_or("--tt-size needs an entry count")?;
+                    let size = size
+                        .parse()
+                        .map_err(|err| format!("invalid cache size '{}': {}", size, err))?;
+
+                    if size == 0 {
+                        return Err("--tt-size needs at least one entry".to_string());
+                    }
+                    options.tt_size = Some(size);
+                }
+                "--win-pause" => {
+                    let millis = args
+                        .next()
+                        .ok_or("--win-pause needs a number of milliseconds")?;
+                    let millis = millis
+                        .parse()
+                        .map_err(|err| format!("invalid win pause '{}': {}", millis, err))?;
+
+                    options.win_pause = Some(Duration::from_millis(millis));
+                }
+                "--auto-restart" => {
+                    let secs = args
+                        .next()
+                        .ok_or("--auto-restart needs a number of seconds")?;
+                    let secs = secs
+                        .parse()
+                        .map_err(|err| format!("invalid auto-restart delay '{}': {}", secs, err))?;
+
+                    options.auto_restart = Some(secs);
+                }
+                "--from-bytes" => {
+                    let path = args.next().ok_or("--from-bytes needs a file")?;
+                    let bytes = std::fs::read(&path)
+                        .map_err(|err| format!("failed to read {}: {}", path, err))?;
+                    let game = Game::from_bytes(bytes, BOARD_WIDTH, BOARD_HEIGHT)
+                        .map_err(|err| format!("invalid board in {}: {}", path, err))?;
+
+                    options.start_board = Some(game.board);
+                }
+                "--check" => options.check = true,
+                "--verify-log" => {
+                    let path = args.next().ok_or("--verify-log needs a log file")?;
+                    options.verify_log = Some(path);
+                }
+                "--diff-games" => {
+                    let first = args.next().ok_or("--diff-games needs two save files")?;
+                    let second = args.next().ok_or("--diff-games needs two save files")?;
+                    options.diff_games = Some((first, second));
+                }
+                "--bench-replay" => {
+                    let dir = args.next().ok_or("--bench-replay needs a directory")?;
+                    options.bench_replay = Some(dir);
+                }
+                "--win-length-orthogonal" | "--win-length-diagonal" => {
+                    let length = args.next().ok_or(format!("{} needs a length", arg))?;
+                    let length = length
+                        .parse()
+                        .map_err(|err| format!("invalid win length '{}': {}", length, err))?;
+
+                    if arg == "--win-length-orthogonal" {
+                        options.win_length_orthogonal = Some(length);
+                    } else {
+                        options.win_length_diagonal = Some(length);
+                    }
+                }
+                "--handicap" => {
+                    let columns = args.next().ok_or("--handicap needs a list of columns")?;
+
+                    options.handicap = columns
+                        .split(',')
+                        .map(|column| {
+                            column
+                                .trim()
+                                .parse()
+                                .map_err(|err| format!("{}", err))
+                                .and_then(|number| {
+                                    Column::from_user(number).map_err(|err| err.to_string())
+                                })
+                                .map_err(|err| {
+                                    format!("invalid handicap column '{}': {}", column, err)
+                                })
+                        })
+                        .collect::<Result<_, _>>()?;
+                }
+                "--fill-order" => {
+                    let order = args.next().ok_or("--fill-order needs an order")?;
+
+                    options.fill_order = Some(match order.as_str() {
+                        "center" => center_out_columns(),
+                        "left" => (0..BOARD_WIDTH).map(Column).collect(),
+                        "right" => (0..BOARD_WIDTH).rev().map(Column).collect(),
+                        _ => parse_fill_order(&order)?,
+                    });
+                }
+                "--turn-limit" => {
+                    let limit = args.next().ok_or("--turn-limit needs a move count")?;
+                    let limit = limit
+                        .parse()
+                        .map_err(|err| format!("invalid turn limit '{}': {}", limit, err))?;
+
+                    options.turn_limit = Some(limit);
+                }
+                "--draw-cooldown" => {
+                    let moves = args.next().ok_or("--draw-cooldown needs a move count")?;
+                    let moves = moves
+                        .parse()
+                        .map_err(|err| format!("invalid draw cooldown '{}': {}", moves, err))?;
+
+                    options.draw_cooldown = Some(moves);
+                }
+                "--mercy" => {
+                    let margin = args.next().ok_or("--mercy needs a score margin")?;
+                    let margin = margin
+                        .parse()
+                        .map_err(|err| format!("invalid mercy margin '{}': {}", margin, err))?;
+
+                    options.mercy = Some(margin);
+                }
+                "--komi" => {
+                    let komi = args.next().ok_or("--komi needs a bonus")?;
+                    let komi = komi
+                        .parse()
+                        .map_err(|err| format!("invalid komi '{}': {}", komi, err))?;
+
+                    options.komi = Some(komi);
+                }
+                "--clock" => {
+                    let spec = args.next().ok_or("--clock needs a time like 5:00+3")?;
+                    options.clock = Some(Clock::parse(&spec)?);
+                }
+                "--export-sgf" => {
+                    let path = args.next().ok_or("--export-sgf needs a file path")?;
+                    options.export_sgf = Some(path);
+                }
+                "--export-frames" => {
+                    let dir = args.next().ok_or("--export-frames needs a directory")?;
+                    options.export_frames = Some(dir);
+                }
+                "--auto-forced" => options.auto_forced = true,
+                "--coach" => options.coach = true,
+                "--safe" => options.safe = true,
+                "--rated" => options.rated = true,
+                "--swap2" => options.swap2 = true,
+                "--think-map" => options.think_map = true,
+                "--tui" => options.tui = true,
+                "--compact" => options.display.compact = true,
+                "-v" | "--verbose" => {
+                    options.verbosity = match options.verbosity {
+                        Verbosity::Quiet => {
+                            return Err("--quiet and --verbose don't mix".to_string())
+                        }
+                        Verbosity::Normal => Verbosity::Verbose,
+                        _ => Verbosity::VeryVerbose,
+                    };
+                }
+                "--quiet" => {
+                    if options.verbosity > Verbosity::Normal {
+                        return Err("--quiet and --verbose don't mix".to_string());
+                    }
+                    options.verbosity = Verbosity::Quiet;
+                }
+                "--protocol" => options.protocol = true,
+                "--postgame-analysis" => options.postgame_analysis = true,
+                "--frame-style" => {
+                    let style = args.next().ok_or("--frame-style needs a style")?;
+
+                    options.display.frame_style = match style.as_str() {
+                        "ascii" => FrameStyle::Ascii,
+                        "box" => FrameStyle::Box,
+                        "none" => FrameStyle::None,
+                        _ => {
+                            return Err(format!(
+                                "unknown frame style '{}' (try ascii, box or none)",
+                                style
+                            ))
+                        }
+                    };
+                }
+                "--train" => {
+                    let pattern = args.next().ok_or("--train needs a pattern")?;
+
+                    options.train = Some(match pattern.as_str() {
+                        "win" => TrainPattern::Win,
+                        "block" => TrainPattern::Block,
+                        _ => {
+                            return Err(format!(
+                                "unknown training pattern '{}' (try win or block)",
+                                pattern
+                            ))
+                        }
+                    });
+                }
+                "--daily" => options.daily = true,
+                "--mode" => {
+                    let mode = args.next().ok_or("--mode needs a game")?;
+
+                    options.mode = match mode.as_str() {
+                        "connect4" => GameMode::ConnectFour,
+                        "dots" => GameMode::Dots,
+                        _ => return Err(format!("unknown mode '{}' (try connect4 or dots)", mode)),
+                    };
+                }
+                "--confirm" => options.confirm = true,
+                "--column-lock" => options.column_lock = true,
+                "--stats" => options.stats = true,
+                "--annotate" => options.annotate = true,
+                "--drill" => options.drill = true,
+                "--warn-blocks" => options.warn_blocks = true,
+                "--warn-forced-loss" => options.warn_forced_loss = true,
+                "--timeline" => options.timeline = true,
+                "--strict-report" => options.strict_report = true,
+                "--analyze" => options.analyze = true,
+                "--no-diagonals" => {
+                    options.win_directions = options
+                        .win_directions
+                        .without(WinDirections::DIAGONAL_DOWN)
+                        .without(WinDirections::DIAGONAL_UP);
+                }
+                "--animate" => options.animate = true,
+                "--color" => options.colour = Some(true),
+                "--no-color" => options.colour = Some(false),
+                "--grow-up" => options.display.grow_up = true,
+                "--show-move-numbers" => options.display.show_move_numbers = true,
+                "--pressure" => options.display.show_pressure = true,
+                "--hud" => options.display.show_hud = true,
+                "--threat-count" => options.display.show_threat_count = true,
+                "--mark-center" => options.display.mark_center = true,
+                "--show-dead-cells" => options.display.show_dead_cells = true,
+                "--autosave" => options.autosave = true,
+                "--resume" => options.resume = true,
+                "--on-retries=forfeit" => options.retry_limit = RetryLimit::Forfeit,
+                "--on-retries=exit" => options.retry_limit = RetryLimit::Exit,
+                "--chaos" => options.chaos = true,
+                "--exact-length" => options.exact_length = true,
+                "--wrap" => options.wrap = true,
+                "--center-gravity" => options.center_gravity = true,
+                "--bounce=left" => options.bounce = Some(Bounce::Left),
+                "--bounce=right" => options.bounce = Some(Bounce::Right),
+                "--tilt=left" => options.tilt = Some(Tilt::Left),
+                "--tilt=right" => options.tilt = Some(Tilt::Right),
+                "--tiebreak=draw" => options.tiebreak = Tiebreak::Draw,
+                "--tiebreak=longest" => options.tiebreak = Tiebreak::LongestRun,
+                _ => return Err(format!("unknown argument '{}'", arg)),
+            }
+        }
+
+        if options.postgame_analysis && (options.analyze || options.verbosity > Verbosity::Normal) {
+            return Err(
+                "--postgame-analysis keeps evaluations hidden until the end, so it can't be used with --analyze or --verbose"
+                    .to_string(),
+            );
+        }
+
+        if options.rated && options.confirm {
+            return Err("--confirm can't be used in --rated games".to_string());
+        }
+
+        if options.confirm && options.column_lock {
+            return Err("--confirm and --column-lock both ask twice, pick one".to_string());
+        }
+
+        if options.daily
+            && (options.challenge.is_some()
+                || options.start_board.is_some()
+                || options.load.is_some())
+        {
+            return Err("--daily sets up its own position, leave out the others".to_string());
+        }
+
+        // Catch bad settings before any game is set up with them.
+        options
+            .new_config()
+            .build()
+            .map_err(|err| err.to_string())?;
+
+        Ok(options)
+    }
+
+    // Whether `player`'s moves come from somewhere other than the keyboard,
+    // at least to begin with.
+    fn scripted(&self, player: Player) -> bool {
+        match player {
+            Player::One => self.scripts[0].is_some(),
+            Player::Two => self.scripts[1].is_some() || self.drill || self.lines.is_some(),
+            Player::None => false,
+        }
+    }
+
+    // A scripted opponent would just reply the same way again, so undoing
+    // against one also takes back the move before.
+    fn takeback_plies(&self, game: &Game) -> usize {
+        if self.scripted(game.next_player()) && !self.scripted(game.current_player) {
+            2
+        } else {
+            1
+        }
+    }
+
+    // How long to hold the final board of a game that has just ended. A
+    // pause of zero leaves the menu to follow straight away, as without one.
+    fn win_pause(&self, just_finished: bool) -> Option<Duration> {
+        self.win_pause
+            .filter(|pause| just_finished && !pause.is_zero())
+    }
+
+    // Names and notes given on the command line win over a loaded header.
+    fn meta(&self, mut meta: GameMeta) -> GameMeta {
+        let [p1_name, p2_name] = &self.names;
+
+        meta.p1_name = p1_name.clone().or(meta.p1_name);
+        meta.p2_name = p2_name.clone().or(meta.p2_name);
+        meta.note = self.note.clone().or(meta.note);
+        meta
+    }
+
+    fn config(&self) -> GameConfigBuilder {
+        GameConfigBuilder::new()
+            .meta(self.meta(GameMeta::default()))
+            .tiebreak(self.tiebreak)
+            .tilt(self.tilt)
+            .chaos(self.chaos)
+            .turn_limit(self.turn_limit)
+            .komi(self.komi)
+            .mercy(self.mercy)
+            .display(self.display)
+            .draw_cooldown(self.draw_cooldown)
+            .clock(self.clock)
+            .win_directions(self.win_directions)
+            .win_lengths(
+                self.win_length_orthogonal.unwrap_or(WIN_LENGTH),
+                self.win_length_diagonal.unwrap_or(WIN_LENGTH),
+            )
+            .exact_length(self.exact_length)
+            .wrap(self.wrap)
+            .center_gravity(self.center_gravity)
+            .bounce(self.bounce)
+            .fill_order(self.fill_order.clone())
+    }
+
+    // Whether each new game opens with a position to find the winning move
+    // in, from --challenge or --daily.
+    fn challenged(&self) -> bool {
+        self.challenge.is_some() || self.daily
+    }
+
+    // A loaded game's handicap is already in its start board, so only new
+    // games take one.
+    fn new_config(&self) -> GameConfigBuilder {
+        let mut config = self.config().handicap(self.handicap.clone());
+
+        if let Some(diagram) = self.challenge {
+            config = config.diagram(diagram);
+        }
+        if let Some(board) = self.start_board {
+            config = config.start_board(board);
+        }
+
+        config
+    }
+
+    fn new_game(&self) -> Game {
+        self.new_config()
+            .build()
+            .expect("new games are validated up front")
+    }
+
+    // Replays a loaded game under the configured rules, so it ends the same
+    // way it would have been played live. Win lengths given on the command
+    // line win over the game's own, which only a game record can carry.
+    fn configure(&self, game: Game) -> Result<Game, LoadError> {
+        self.config()
+            .win_lengths(
+                self.win_length_orthogonal
+                    .unwrap_or(game.win_length_orthogonal),
+                self.win_length_diagonal.unwrap_or(game.win_length_diagonal),
+            )
+            .meta(self.meta(game.meta))
+            .start_board(game.start_board)
+            .moves(game.history)
+            .build()
+            .map_err(LoadError::Rejected)
+    }
+}
+
+fn read_input(input: &mut impl BufRead) -> Result<String, GameError> {
+    let mut line = String::new();
+
+    loop {
+        match input.read_line(&mut line) {
+            Ok(0) => return Err(GameError::InputClosed),
+            Ok(_) => return Ok(line),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+// Stdin is read on a background thread so timed playback can wait for a key
+// with a timeout. Every stdin read goes through this channel, which keeps
+// lines in order whoever asks for them.
+fn stdin_lines() -> &'static Mutex<mpsc::Receiver<Result<String, GameError>>> {
+    static LINES: OnceLock<Mutex<mpsc::Receiver<Result<String, GameError>>>> = OnceLock::new();
+
+    LINES.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut history = InputHistory::new(INPUT_HISTORY_CAPACITY);
+
+            loop {
+                let line = if tui() {
+                    read_tui_input(&mut io::stdin().lock(), &mut history)
+                } else {
+                    read_input(&mut io::stdin().lock())
+                };
+                let closed = line.is_err();
+
+                if sender.send(line).is_err() || closed {
+                    break;
+                }
+            }
+        });
+
+        Mutex::new(receiver)
+    })
+}
+
+// Asks for the names of players typing at the keyboard who weren't named on
+// the command line. An empty answer keeps "Player N".
+fn prompt_names(options: &mut Options) -> Result<(), GameError> {
+    for (index, player) in [Player::One, Player::Two].into_iter().enumerate() {
+        if options.scripted(player) || options.names[index].is_some() {
+            continue;
+        }
+
+        println!("Player {} name (Enter to skip):", index + 1);
+        let name = read_stdin()?.trim().to_string();
+
+        if !name.is_empty() {
+            options.names[index] = Some(name);
+        }
+    }
+
+    Ok(())
+}
+
+// Set by --tui before the first frame is drawn or line is read.
+static TUI: OnceLock<bool> = OnceLock::new();
+
+fn tui() -> bool {
+    *TUI.get_or_init(|| false)
+}
+
+// Where the last TUI frame put the board and whether the button bar was
+// live, in 1-based terminal rows.
+struct TuiLayout {
+    board_rows: std::ops::Range<usize>,
+    buttons: bool,
+}
+
+static TUI_LAYOUT: Mutex<TuiLayout> = Mutex::new(TuiLayout {
+    board_rows: 0..0,
+    buttons: false,
+});
+
+impl TuiLayout {
+    // The line a click stands for. Every cell is padded to `CELL_WIDTH`
+    // terminal columns whatever the disc glyph is, so the column follows from
+    // `x` alone; the gap after a cell counts as part of it.
+    fn click(&self, x: usize, y: usize) -> Option<String> {
+        if y == 1 && self.buttons {
+            let mut end = 0;
+
+            for (label, input) in TUI_BUTTONS {
+                let start = end + 1;
+                end = start + display_width(label) - 1;
+
+                if (start..=end).contains(&x) {
+                    return Some(input.to_string());
+                }
+                end += 2;
+            }
+
+            return None;
+        }
+
+        let column = x.checked_sub(1)? / (CELL_WIDTH + 1);
+
+        (self.board_rows.contains(&y) && column < BOARD_WIDTH).then(|| (column + 1).to_string())
+    }
+}
+
+// The terminal modes in place before --tui took over, put back on drop so
+// quitting, errors and panics all leave a usable shell.
+struct TuiGuard {
+    saved: String,
+}
+
+impl TuiGuard {
+    fn enter() -> io::Result<TuiGuard> {
+        let saved = stty(&["-g"])?.trim().to_string();
+        stty(&["-icanon", "-echo"])?;
+        TUI.set(true)
+            .expect("the TUI is entered before any input is read");
+
+        print!("{}", MOUSE_ON);
+        io::stdout().flush()?;
+        Ok(TuiGuard { saved })
+    }
+}
+
+impl Drop for TuiGuard {
+    fn drop(&mut self) {
+        print!("{}", MOUSE_OFF);
+        let _ = io::stdout().flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = std::process::Command::new("stty")
+        .args(args)
+        .stdin(std::process::Stdio::inherit())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Lines typed at the TUI prompt, recalled with the up and down arrows like
+// a shell. Only lines that parse as game commands are kept, so typos don't
+// crowd out moves, and repeating the last line doesn't store it twice.
+struct InputHistory {
+    entries: VecDeque<String>,
+    capacity: usize,
+    // The entry on show, or `None` while typing a fresh line.
+    cursor: Option<usize>,
+}
+
+impl InputHistory {
+    fn new(capacity: usize) -> InputHistory {
+        InputHistory {
+            entries: VecDeque::new(),
+            capacity,
+            cursor: None,
+        }
+    }
+
+    fn push(&mut self, line: &str) {
+        self.cursor = None;
+
+        if matches!(parse_command(line), Command::Invalid(_))
+            || self.entries.back().is_some_and(|last| last == line)
+        {
+            return;
+        }
+
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(line.to_string());
+    }
+
+    // Steps back to an older entry, staying on the oldest once there.
+    fn older(&mut self) -> Option<&str> {
+        let index = match self.cursor {
+            None => self.entries.len().checked_sub(1)?,
+            Some(index) => index.saturating_sub(1),
+        };
+
+        self.cursor = Some(index);
+        self.entries.get(index).map(String::as_str)
+    }
+
+    // Steps forward to a newer entry. Going past the newest comes back to an
+    // empty line, and there is nothing newer than that.
+    fn newer(&mut self) -> Option<&str> {
+        let index = self.cursor? + 1;
+
+        if index < self.entries.len() {
+            self.cursor = Some(index);
+            self.entries.get(index).map(String::as_str)
+        } else {
+            self.cursor = None;
+            Some("")
+        }
+    }
+}
+
+enum TuiKey {
+    Click(String),
+    Up,
+    Down,
+}
+
+// With the terminal no longer echoing or buffering lines, keys are echoed
+// and assembled into lines here. A left click stands in for a whole line.
+fn read_tui_input(
+    input: &mut impl BufRead,
+    history: &mut InputHistory,
+) -> Result<String, GameError> {
+    let mut line = Vec::new();
+    let mut bytes = input.bytes();
+    let mut out = io::stdout();
+
+    loop {
+        let byte = match bytes.next() {
+            None => return Err(GameError::InputClosed),
+            Some(Err(err)) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Some(Err(err)) => return Err(err.into()),
+            Some(Ok(byte)) => byte,
+        };
+
+        match byte {
+            b'\x1b' => {
+                let recalled = match read_escape(&mut bytes)? {
+                    Some(TuiKey::Click(click)) => return Ok(click),
+                    Some(TuiKey::Up) => history.older().map(str::to_string),
+                    Some(TuiKey::Down) => history.newer().map(str::to_string),
+                    None => None,
+                };
+
+                // Rub out the line on show and type the recalled one over it.
+                if let Some(recalled) = recalled {
+                    let shown = display_width(&String::from_utf8_lossy(&line));
+                    print!("{}{}", "\x08 \x08".repeat(shown), recalled);
+                    line = recalled.into_bytes();
+                }
+            }
+            b'\n' | b'\r' => {
+                println!();
+                let line = String::from_utf8_lossy(&line).into_owned();
+                history.push(line.trim());
+                return Ok(line);
+            }
+            0x7f | 0x08 => {
+                // Drop the continuation bytes of a multi-byte character too.
+                while line.pop().is_some_and(|byte| byte & 0xc0 == 0x80) {}
+                print!("\x08 \x08");
+            }
+            byte => {
+                line.push(byte);
+                out.write_all(&[byte])?;
+            }
+        }
+        out.flush()?;
+    }
+}
+
+// Reads the rest of an escape sequence. Only the up and down arrows, in
+// either cursor key mode, and SGR mouse reports of a left button press
+// (`[<0;x;yM`) mean anything. Other keys are swallowed, as are clicks
+// outside the board and live buttons.
+fn read_escape(
+    bytes: &mut impl Iterator<Item = io::Result<u8>>,
+) -> Result<Option<TuiKey>, GameError> {
+    let mut sequence = Vec::new();
+
+    for byte in bytes {
+        let byte = byte?;
+        sequence.push(byte);
+
+        match sequence[0] {
+            b'[' if sequence.len() > 1 && (0x40..=0x7e).contains(&byte) => break,
+            b'O' if sequence.len() == 2 => break,
+            b'[' | b'O' => {}
+            _ => return Ok(None),
+        }
+    }
+
+    match &sequence[..] {
+        b"[A" | b"OA" => return Ok(Some(TuiKey::Up)),
+        b"[B" | b"OB" => return Ok(Some(TuiKey::Down)),
+        _ => {}
+    }
+
+    let report = String::from_utf8_lossy(&sequence);
+    let Some(fields) = report
+        .strip_prefix("[<")
+        .and_then(|rest| rest.strip_suffix('M'))
+    else {
+        return Ok(None);
+    };
+
+    let fields: Vec<usize> = fields.split(';').filter_map(|n| n.parse().ok()).collect();
+
+    match fields[..] {
+        [0, x, y] => Ok(TUI_LAYOUT
+            .lock()
+            .expect("TUI layout lock poisoned")
+            .click(x, y)
+            .map(TuiKey::Click)),
+        _ => Ok(None),
+    }
+}
+
+fn read_stdin() -> Result<String, GameError> {
+    stdin_lines()
+        .lock()
+        .expect("stdin reader lock poisoned")
+        .recv()
+        .unwrap_or(Err(GameError::InputClosed))
+}
+
+// Like `read_stdin`, but gives up with `None` once `timeout` has passed.
+fn read_stdin_timeout(timeout: Duration) -> Option<Result<String, GameError>> {
+    match stdin_lines()
+        .lock()
+        .expect("stdin reader lock poisoned")
+        .recv_timeout(timeout)
+    {
+        Ok(line) => Some(line),
+        Err(mpsc::RecvTimeoutError::Timeout) => None,
+        Err(mpsc::RecvTimeoutError::Disconnected) => Some(Err(GameError::InputClosed)),
+    }
+}
+
+enum Command {
+    Drop(Column),
+    Cancel,
+    Snapshot,
+    OfferDraw,
+    Save,
+    Dump,
+    Threats,
+    Describe,
+    Undo,
+    Redo,
+    Branch,
+    Invalid(String),
+}
+
+fn parse_command(line: &str) -> Command {
+    let line = line.trim();
+
+    if line.eq_ignore_ascii_case("s") {
+        return Command::Save;
+    }
+
+    if line.eq_ignore_ascii_case("d") {
+        return Command::Dump;
+    }
+
+    if line.eq_ignore_ascii_case("u") {
+        return Command::Undo;
+    }
+
+    if line.eq_ignore_ascii_case("y") {
+        return Command::Redo;
+    }
+
+    if line.eq_ignore_ascii_case("t") {
+        return Command::Threats;
+    }
+
+    if line == "?" {
+        return Command::Describe;
+    }
+
+    if line == "=" {
+        return Command::OfferDraw;
+    }
+
+    if line.eq_ignore_ascii_case("p") {
+        return Command::Snapshot;
+    }
+
+    if line.eq_ignore_ascii_case("c") {
+        return Command::Cancel;
+    }
+
+    if line.eq_ignore_ascii_case("b") {
+        return Command::Branch;
+    }
+
+    match line.parse::<usize>() {
+        Ok(number) => match Column::from_user(number) {
+            Ok(column) => Command::Drop(column),
+            Err(err) => Command::Invalid(err.to_string()),
+        },
+        Err(err) => Command::Invalid(err.to_string()),
+    }
+}
+
+trait MoveSource {
+    fn next_move(&mut self, game: &Game) -> Result<Command, GameError>;
+}
+
+// Told what happens in a game as `run` plays it, for anything that reacts
+// to play without taking part in it. A game that ends in a win or a draw
+// gets exactly one of `on_win` and `on_draw`, however it ended.
+trait GameObserver {
+    fn on_reset(&mut self, _game: &Game) {}
+
+    fn on_move(&mut self, _game: &Game, _player: Player, _column: Column, _landed: (usize, usize)) {
+    }
+
+    fn on_win(&mut self, _game: &Game, _winner: Player) {}
+
+    fn on_draw(&mut self, _game: &Game) {}
+}
+
+fn notify_reset(observers: &mut [Box<dyn GameObserver>], game: &Game) {
+    for observer in observers.iter_mut() {
+        observer.on_reset(game);
+    }
+}
+
+fn notify_move(observers: &mut [Box<dyn GameObserver>], game: &Game) {
+    if let (Some(&column), Some(landed)) = (game.history.last(), game.last_landing()) {
+        for observer in observers.iter_mut() {
+            observer.on_move(game, game.last_mover(), column, landed);
+        }
+    }
+}
+
+fn notify_end(observers: &mut [Box<dyn GameObserver>], game: &Game) {
+    for observer in observers.iter_mut() {
+        match game.outcome() {
+            GameOutcome::Win(winner) => observer.on_win(game, winner),
+            GameOutcome::Draw => observer.on_draw(game),
+            GameOutcome::Ongoing => {}
+        }
+    }
+}
+
+struct StdinSource;
+
+impl MoveSource for StdinSource {
+    fn next_move(&mut self, _game: &Game) -> Result<Command, GameError> {
+        let line = read_stdin()?;
+        Ok(parse_command(&line))
+    }
+}
+
+struct ScriptSource {
+    lines: std::vec::IntoIter<String>,
+}
+
+impl ScriptSource {
+    fn from_file(path: &str) -> io::Result<ScriptSource> {
+        let lines: Vec<String> = std::fs::read_to_string(path)?
+            .lines()
+            .map(str::to_string)
+            .collect();
+
+        Ok(ScriptSource {
+            lines: lines.into_iter(),
+        })
+    }
+}
+
+impl MoveSource for ScriptSource {
+    fn next_move(&mut self, _game: &Game) -> Result<Command, GameError> {
+        let line = self.lines.next().ok_or(GameError::InputClosed)?;
+        Ok(parse_command(&line))
+    }
+}
+
+// Recorded opening lines sharing common prefixes. Children keep the order
+// the lines were read in, so the first line listed wins a tie.
+#[derive(Default)]
+struct LineTrie {
+    children: Vec<(Column, LineTrie)>,
+}
+
+impl LineTrie {
+    // One line per row as space-separated columns, e.g. `4 4 3 5`. Blank
+    // rows and rows starting with `#` are skipped.
+    fn from_file(path: &str) -> Result<LineTrie, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let mut trie = LineTrie::default();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let moves = line
+                .split_whitespace()
+                .map(|field| field.parse().map_err(|err| format!("'{}': {}", field, err)))
+                .collect::<Result<Vec<usize>, String>>()
+                .map_err(|err| format!("line {}: {}", number + 1, err))?;
+            let game = Game::verify_replay(&moves).map_err(|(index, err)| {
+                format!("line {}, move {}: {}", number + 1, index + 1, err)
+            })?;
+
+            trie.insert(&game.history);
+        }
+
+        Ok(trie)
+    }
+
+    fn insert(&mut self, moves: &[Column]) {
+        if let Some((&column, rest)) = moves.split_first() {
+            let index = match self.children.iter().position(|(child, _)| *child == column) {
+                Some(index) => index,
+                None => {
+                    self.children.push((column, LineTrie::default()));
+                    self.children.len() - 1
+                }
+            };
+
+            self.children[index].1.insert(rest);
+        }
+    }
+
+    fn follow(&self, moves: &[Column]) -> Option<&LineTrie> {
+        moves.iter().try_fold(self, |node, column| {
+            node.children
+                .iter()
+                .find(|(child, _)| child == column)
+                .map(|(_, child)| child)
+        })
+    }
+}
+
+// Plays Player 2 from recorded lines while the game stays on one of them,
+// then hands the turn back to stdin for the rest of the game.
+struct LinesSource {
+    lines: LineTrie,
+    left_book: bool,
+}
+
+impl MoveSource for LinesSource {
+    fn next_move(&mut self, game: &Game) -> Result<Command, GameError> {
+        if !self.left_book {
+            let reply = self
+                .lines
+                .follow(&game.history)
+                .and_then(|node| node.children.first());
+
+            match reply {
+                Some(&(column, _)) if game.preview(column).is_some() => {
+                    println!(
+                        "{}Lines: following the book with column {}{}",
+                        escape(ORANGE),
+                        column.to_user(),
+                        escape(RESET)
+                    );
+                    return Ok(Command::Drop(column));
+                }
+                _ => {
+                    self.left_book = true;
+                    println!(
+                        "{}Lines: out of book, over to you{}",
+                        escape(ORANGE),
+                        escape(RESET)
+                    );
+                }
+            }
+        }
+
+        StdinSource.next_move(game)
+    }
+}
+
+// Replays Player 2's side of a recorded game move for move. Once a logged
+// move is no longer playable, or the log runs out, the turn goes back to
+// stdin for the rest of the game.
+struct DrillSource {
+    moves: Vec<Column>,
+    diverged: bool,
+}
+
+impl MoveSource for DrillSource {
+    fn next_move(&mut self, game: &Game) -> Result<Command, GameError> {
+        if !self.diverged {
+            match self.moves.get(game.history.len()) {
+                Some(&column) if game.preview(column).is_some() => {
+                    println!(
+                        "{}Drill: replaying column {}{}",
+                        escape(ORANGE),
+                        column.to_user(),
+                        escape(RESET)
+                    );
+                    return Ok(Command::Drop(column));
+                }
+                _ => {
+                    self.diverged = true;
+                    println!(
+                        "{}Drill: the logged game no longer applies, over to you{}",
+                        escape(ORANGE),
+                        escape(RESET)
+                    );
+                }
+            }
+        }
+
+        StdinSource.next_move(game)
+    }
+}
+
+// Saves go to a temporary file that is renamed over the target, so a crash
+// mid-write never leaves a truncated save behind. Saves in this process take
+// turns, and the temporary name carries the process id, so two writers never
+// share a temporary file and whichever renames last wins whole.
+fn write_save(game: &Game, path: &str) -> io::Result<()> {
+    static SAVE_LOCK: Mutex<()> = Mutex::new(());
+
+    let _guard = SAVE_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let temp_path = format!("{}.{}.tmp", path, std::process::id());
+    let mut file = std::fs::File::create(&temp_path)?;
+
+    game.save_binary(&mut file)?;
+    file.sync_all()?;
+
+    std::fs::rename(&temp_path, path)
+}
+
+fn load_game(path: &str) -> Result<Game, LoadError> {
+    if path.ends_with(".sgf") {
+        return Game::from_sgf(&std::fs::read_to_string(path)?).map_err(LoadError::InvalidRecord);
+    }
+
+    let file = std::fs::File::open(path)?;
+    Game::load_binary(&mut io::BufReader::new(file))
+}
+
+fn escape_sgf(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(']', "\\]")
+}
+
+// Splits an SGF-style record into its nodes, each a list of properties in
+// the order given. Inside a value a backslash escapes the next character.
+fn parse_sgf_nodes(text: &str) -> Result<Vec<Vec<(String, String)>>, SgfError> {
+    let malformed = |reason: &str| SgfError::Malformed(reason.to_string());
+    let body = text
+        .trim()
+        .strip_prefix('(')
+        .and_then(|body| body.strip_suffix(')'))
+        .ok_or_else(|| malformed("a record is wrapped in parentheses"))?;
+
+    let mut nodes: Vec<Vec<(String, String)>> = Vec::new();
+    let mut chars = body.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            ';' => nodes.push(Vec::new()),
+            c if c.is_ascii_uppercase() => {
+                let mut property = c.to_string();
+                while let Some(c) = chars.next_if(char::is_ascii_uppercase) {
+                    property.push(c);
+                }
+
+                if chars.next() != Some('[') {
+                    return Err(SgfError::Malformed(format!(
+                        "property {} has no value",
+                        property
+                    )));
+                }
+
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => value.push(
+                            chars
+                                .next()
+                                .ok_or_else(|| malformed("the record ends inside a value"))?,
+                        ),
+                        Some(']') => break,
+                        Some(c) => value.push(c),
+                        None => return Err(malformed("the record ends inside a value")),
+                    }
+                }
+
+                nodes
+                    .last_mut()
+                    .ok_or_else(|| malformed("properties must follow a ';'"))?
+                    .push((property, value));
+            }
+            c => return Err(SgfError::Malformed(format!("unexpected '{}'", c))),
+        }
+    }
+
+    Ok(nodes)
+}
+
+// 64-bit FNV-1a. Not cryptographic, but stable across builds, which is all
+// the rated log needs to show that a line was edited after the fact.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// Each log line ends with a hash of the previous line's hash and its own
+// entry, so changing, dropping or reordering a line breaks every hash after.
+fn chain_hash(previous: u64, entry: &str) -> u64 {
+    fnv1a(format!("{:016x} {}", previous, entry).as_bytes())
+}
+
+// Checks a rated log line by line. Gives the final hash, which serves as the
+// certificate, or the number of the first line whose hash doesn't match.
+fn verify_log(text: &str) -> Result<u64, usize> {
+    let mut hash = 0;
+
+    for (number, line) in text.lines().enumerate() {
+        let (entry, recorded) = line.rsplit_once(' ').ok_or(number + 1)?;
+        let expected = chain_hash(hash, entry);
+
+        if u64::from_str_radix(recorded, 16) != Ok(expected) {
+            return Err(number + 1);
+        }
+        hash = expected;
+    }
+
+    Ok(hash)
+}
+
+// Append-only move log for rated games. New entries carry on the chain of
+// whatever is already in the file.
+struct RatedLog {
+    file: std::fs::File,
+    hash: u64,
+}
+
+impl RatedLog {
+    fn open(path: &str) -> io::Result<RatedLog> {
+        let existing = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        let hash = verify_log(&existing)
+            .map_err(|line| io::Error::other(format!("line {} fails verification", line)))?;
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+
+        Ok(RatedLog { file, hash })
+    }
+
+    fn record(&mut self, entry: &str) -> io::Result<()> {
+        let hash = chain_hash(self.hash, entry);
+
+        writeln!(self.file, "{} {:016x}", entry, hash)?;
+        self.file.sync_all()?;
+        self.hash = hash;
+        Ok(())
+    }
+
+    fn log(&mut self, game: &Game, entry: &str) {
+        if let Err(err) = self.record(entry) {
+            game.clone()
+                .display_error(format!("failed to write the rated log: {}", err));
+        }
+    }
+
+    fn log_result(&mut self, game: &Game) {
+        self.log(game, &format!("result {}", game.result()));
+        println!(
+            "{}Certificate: {:016x}{}",
+            escape(ORANGE),
+            self.hash,
+            escape(RESET)
+        );
+    }
+}
+
+// The rated log writes a line for each game, move and result, and shows
+// the running certificate once a game is over.
+impl GameObserver for RatedLog {
+    fn on_reset(&mut self, game: &Game) {
+        self.log(
+            game,
+            &format!("game {} {}", today(), game.board_fingerprint()),
+        );
+    }
+
+    fn on_move(&mut self, game: &Game, _player: Player, column: Column, _landed: (usize, usize)) {
+        self.log(
+            game,
+            &format!("move {} column {}", game.current_move, column.to_user()),
+        );
+    }
+
+    fn on_win(&mut self, game: &Game, _winner: Player) {
+        self.log_result(game);
+    }
+
+    fn on_draw(&mut self, game: &Game) {
+        self.log_result(game);
+    }
+}
+
+fn autosave(game: &mut Game, options: &Options) {
+    if !options.autosave {
+        return;
+    }
+
+    if let Err(err) = write_save(game, AUTOSAVE_PATH) {
+        game.display_error(format!("failed to autosave: {}", err));
+    }
+}
+
+fn save_game(game: &mut Game, path: &str) {
+    match write_save(game, path) {
+        Ok(()) => {
+            game.display_board();
+            println!("{}Game saved to {}{}", escape(ORANGE), path, escape(RESET));
+        }
+        Err(err) => game.display_error(format!("failed to save to {}: {}", path, err)),
+    }
+}
+
+// A line protocol for bots driving the engine as a subprocess: one command
+// per line in, one response line per command out, flushed straight away.
+fn run_protocol(
+    options: &Options,
+    mut game: Game,
+    input: impl BufRead,
+    mut out: impl Write,
+) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default().to_ascii_uppercase();
+
+        let response = match (command.as_str(), words.next()) {
+            ("MOVE", Some(number)) => match number
+                .parse()
+                .map_err(|err| format!("{}", err))
+                .and_then(|number| Column::from_user(number).map_err(|err| err.to_string()))
+                .and_then(|column| game.play_move(column).map_err(|err| err.to_string()))
+            {
+                Ok(_) => match game.outcome() {
+                    GameOutcome::Ongoing => "OK".to_string(),
+                    GameOutcome::Win(winner) => format!("WIN {}", winner as u8),
+                    GameOutcome::Draw => "DRAW".to_string(),
+                },
+                Err(err) => format!("ILLEGAL {}", err),
+            },
+            ("MOVE", None) => "ERROR MOVE needs a column".to_string(),
+            ("UNDO", None) => match game.undo() {
+                Ok(()) => "OK".to_string(),
+                Err(err) => format!("ILLEGAL {}", err),
+            },
+            ("STATE", None) => format!("STATE {}", game.board_fingerprint()),
+            ("RESET", None) => {
+                game = options.new_game();
+                "OK".to_string()
+            }
+            ("QUIT", None) => {
+                writeln!(out, "OK")?;
+                return out.flush();
+            }
+            _ => format!("ERROR unknown command '{}'", line.trim()),
+        };
+
+        writeln!(out, "{}", response)?;
+        out.flush()?;
+    }
+
+    Ok(())
+}
+
+// The stock engine's self-test for --check. Every check runs over the same
+// seeded random games, so a failure shows up the same way each time.
+const CHECK_GAMES: usize = 200;
+const CHECK_SEED: u64 = 0x0c4e_c4ed;
+
+fn self_check() -> Vec<(&'static str, Result<(), String>)> {
+    let games = match check_games() {
+        Ok(games) => games,
+        Err(err) => return vec![("random games keep the engine invariants", Err(err))],
+    };
+
+    vec![
+        ("random games keep the engine invariants", Ok(())),
+        ("binary saves load back the same game", check_saves(&games)),
+        ("SGF records load back the same game", check_records(&games)),
+        (
+            "move encoding decodes to the same moves",
+            check_encoding(&games),
+        ),
+        (
+            "wins match a direct scan for four in a row",
+            check_wins(&games),
+        ),
+        (
+            "the win search agrees with the winning moves",
+            check_search(&games),
+        ),
+    ]
+}
+
+// Plays random games to the end, checking after every move that the board
+// holds one disc per move and that the turn passes unless the game is over.
+fn check_games() -> Result<Vec<Game>, String> {
+    let mut rng = Rng::from_seed(CHECK_SEED);
+    let mut games = Vec::new();
+
+    for index in 0..CHECK_GAMES {
+        let mut game = Game::default();
+
+        while !game.is_finished {
+            let columns = game.available_columns();
+            let mover = game.current_player;
+            let column = columns[rng.below(columns.len())];
+
+            game.play_move(column).map_err(|err| {
+                format!(
+                    "game {}: column {} failed: {}",
+                    index + 1,
+                    column.to_user(),
+                    err
+                )
+            })?;
+
+            let discs = game
+                .board
+                .iter()
+                .flatten()
+                .filter(|&&cell| cell != 0)
+                .count();
+            if discs != game.current_move as usize {
+                return Err(format!(
+                    "game {}: {} discs after {} moves",
+                    index + 1,
+                    discs,
+                    game.current_move
+                ));
+            }
+            if !game.is_finished && game.current_player == mover {
+                return Err(format!("game {}: the turn didn't pass", index + 1));
+            }
+        }
+
+        games.push(game);
+    }
+
+    Ok(games)
+}
+
+fn check_saves(games: &[Game]) -> Result<(), String> {
+    for (index, game) in games.iter().enumerate() {
+        let mut bytes = Vec::new();
+        game.save_binary(&mut bytes)
+            .map_err(|err| err.to_string())?;
+
+        let loaded = Game::load_binary(&mut &bytes[..])
+            .map_err(|err| format!("game {}: {}", index + 1, err))?;
+        if let Some((ply, reason)) = game.compare_replays(&loaded) {
+            return Err(format!("game {}, ply {}: {}", index + 1, ply, reason));
+        }
+    }
+
+    Ok(())
+}
+
+fn check_records(games: &[Game]) -> Result<(), String> {
+    for (index, game) in games.iter().enumerate() {
+        let loaded =
+            Game::from_sgf(&game.to_sgf()).map_err(|err| format!("game {}: {}", index + 1, err))?;
+        if let Some((ply, reason)) = game.compare_replays(&loaded) {
+            return Err(format!("game {}, ply {}: {}", index + 1, ply, reason));
+        }
+    }
+
+    Ok(())
+}
+
+fn check_encoding(games: &[Game]) -> Result<(), String> {
+    for (index, game) in games.iter().enumerate() {
+        let moves = Game::decode_moves(&game.encode_moves())
+            .map_err(|err| format!("game {}: {}", index + 1, err))?;
+        if moves != game.history {
+            return Err(format!("game {}: the moves came back different", index + 1));
+        }
+    }
+
+    Ok(())
+}
+
+// Looks for four in a row by trying every line of four cells, which is
+// all a stock game needs, and compares the result with the engine's.
+fn check_wins(games: &[Game]) -> Result<(), String> {
+    let four = |board: &Board, player: Player| {
+        (0..BOARD_HEIGHT as isize).any(|row| {
+            (0..BOARD_WIDTH as isize).any(|col| {
+                [(0, 1), (1, 0), (1, 1), (1, -1)]
+                    .iter()
+                    .any(|&(row_step, col_step)| {
+                        (0..4).all(|step| {
+                            let (r, c) = (row + step * row_step, col + step * col_step);
+                            (0..BOARD_HEIGHT as isize).contains(&r)
+                                && (0..BOARD_WIDTH as isize).contains(&c)
+                                && board[r as usize][c as usize] == player as u8
+                        })
+                    })
+            })
+        })
+    };
+
+    for (index, game) in games.iter().enumerate() {
+        let scanned = [Player::One, Player::Two]
+            .into_iter()
+            .find(|&player| four(&game.board, player))
+            .unwrap_or(Player::None);
+
+        if scanned != game.winner {
+            return Err(format!(
+                "game {}: the engine gave {:?}, the scan {:?}",
+                index + 1,
+                game.winner,
+                scanned
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// A win in one found by the search is exactly a move that wins on the spot.
+fn check_search(games: &[Game]) -> Result<(), String> {
+    for (index, game) in games.iter().enumerate() {
+        for ply in 0..game.history.len() {
+            let position = game.position_at(ply);
+            let player = position.current_player;
+
+            if position.forces_win(player, 1) == position.winning_moves(player).is_empty() {
+                return Err(format!(
+                    "game {}, ply {}: the search and the winning moves disagree",
+                    index + 1,
+                    ply
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Loads every save in `dir` up front, then times replaying them through
+// `play_move` alone. Files that fail to load are reported and skipped.
+fn bench_replay(dir: &Path, options: &Options) -> io::Result<()> {
+    let mut games = Vec::new();
+    let mut paths: Vec<_> = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    paths.sort();
+
+    for path in paths.iter().filter(|path| path.is_file()) {
+        match load_game(&path.to_string_lossy()).and_then(|game| options.configure(game)) {
+            Ok(game) => games.push(game),
+            Err(err) => println!("Skipping {}: {}", path.display(), err),
+        }
+    }
+
+    let start = Instant::now();
+    let mut total_moves = 0;
+
+    for game in &games {
+        let mut replay = game.position_at(0);
+
+        for &column in &game.history {
+            replay
+                .play_move(column)
+                .expect("history only contains legal moves");
+        }
+
+        total_moves += game.history.len();
+    }
+
+    let elapsed = start.elapsed().as_secs_f64();
+    println!("Replayed {} games, {} moves", games.len(), total_moves);
+    println!(
+        "{:.0} moves/second",
+        total_moves as f64 / elapsed.max(f64::EPSILON)
+    );
+
+    Ok(())
+}
+
+// Loads two saves under the command-line settings and reports the first ply
+// where they part ways, with the position each game reached there.
+fn diff_games(first: &str, second: &str, options: &Options) -> Result<(), String> {
+    let load = |path: &str| {
+        load_game(path)
+            .and_then(|game| options.configure(game))
+            .map_err(|err| format!("failed to load {}: {}", path, err))
+    };
+    let (a, b) = (load(first)?, load(second)?);
+
+    match a.compare_replays(&b) {
+        Some((ply, description)) => {
+            println!(
+                "{} and {} first differ at move {}: {}",
+                first, second, ply, description
+            );
+            println!("{}: {}", first, a.position_at(ply).board_fingerprint());
+            println!("{}: {}", second, b.position_at(ply).board_fingerprint());
+        }
+        None => println!("{} and {} replay identically", first, second),
+    }
+
+    Ok(())
+}
+
+fn announce_patterns(game: &Game) {
+    for player in [Player::One, Player::Two] {
+        for pattern in game.detect_patterns(player) {
+            println!(
+                "{}Coach: {} has {}{}",
+                escape(ORANGE),
+                game.player_name(player),
+                pattern,
+                escape(RESET)
+            );
+        }
+    }
+}
+
+// Terminal columns taken up by `text`. Without full Unicode width tables,
+// pictographs and the geometric shapes used for discs count as double width
+// and everything else as single. Colour escapes take up no space.
+fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut in_escape = false;
+
+    for c in text.chars() {
+        match (in_escape, c) {
+            (false, '\x1b') => in_escape = true,
+            (true, 'm') => in_escape = false,
+            (true, _) => {}
+            (false, c) => {
+                width += match c as u32 {
+                    0x1F300..=0x1FAFF | 0x2600..=0x27BF => 2,
+                    _ => 1,
+                }
+            }
+        }
+    }
+
+    width
+}
+
+// Pads every cell to `CELL_WIDTH` columns so discs, markers and column
+// numbers line up however wide each glyph is.
+fn render_row<S: AsRef<str>>(cells: &[S]) -> String {
+    cells
+        .iter()
+        .map(|cell| {
+            let cell = cell.as_ref();
+            let padding = CELL_WIDTH.saturating_sub(display_width(cell));
+            format!("{}{}", cell, " ".repeat(padding))
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+        .trim_end()
+        .to_string()
+}
+
+// Rows in the order centre gravity fills them: nearest the middle first and,
+// on an even height, the lower of two equally near rows before the upper.
+fn center_rows() -> Vec<usize> {
+    let mut rows: Vec<usize> = (0..BOARD_HEIGHT).collect();
+    rows.sort_by_key(|&row| ((2 * row).abs_diff(BOARD_HEIGHT - 1), BOARD_HEIGHT - row));
+    rows
+}
+
+// The rules above and below the board, as wide as a row of cells. With no
+// frame the lines are left out altogether.
+fn render_separator(style: FrameStyle) -> Option<String> {
+    let width = BOARD_WIDTH * (CELL_WIDTH + 1) - 1;
+    let line = match style {
+        FrameStyle::Ascii => "-".repeat(width),
+        FrameStyle::Box => "─".repeat(width),
+        FrameStyle::None => return None,
+    };
+
+    Some(format!("{}{}{}", escape(ORANGE), line, escape(RESET)))
+}
+
+// Every column from the middle outwards, the left one first of each pair.
+fn center_out_columns() -> Vec<Column> {
+    let mut columns: Vec<Column> = (0..BOARD_WIDTH).map(Column).collect();
+    columns.sort_by_key(|column| column.0.abs_diff(BOARD_WIDTH / 2));
+    columns
+}
+
+// A fill order given as columns numbered from 1, which must name each
+// column exactly once.
+fn parse_fill_order(order: &str) -> Result<Vec<Column>, String> {
+    let columns: Vec<Column> = order
+        .split(',')
+        .map(|column| {
+            column
+                .trim()
+                .parse()
+                .map_err(|err| format!("{}", err))
+                .and_then(|number| Column::from_user(number).map_err(|err| err.to_string()))
+                .map_err(|err| format!("invalid fill order column '{}': {}", column, err))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let covered = (0..BOARD_WIDTH).all(|index| columns.contains(&Column(index)));
+    if columns.len() != BOARD_WIDTH || !covered {
+        return Err(format!(
+            "--fill-order must list each of the {} columns once",
+            BOARD_WIDTH
+        ));
+    }
+
+    Ok(columns)
+}
+
+// The middle column, or the middle two on an even-width board.
+fn center_columns() -> Vec<usize> {
+    if BOARD_WIDTH % 2 == 1 {
+        vec![BOARD_WIDTH / 2]
+    } else {
+        vec![BOARD_WIDTH / 2 - 1, BOARD_WIDTH / 2]
+    }
+}
+
+fn shade_center(cell: &str) -> String {
+    format!("{}{}{}", escape(CENTER_BACKGROUND), cell, escape(RESET))
+}
+
+// Without colour there is no shading, so the centre columns are marked with
+// a `*` after the number, which still fits the cell.
+fn render_column_numbers(mark_center: bool) -> String {
+    let numbers: Vec<String> = (0..BOARD_WIDTH)
+        .map(|column| {
+            let number = (column + 1).to_string();
+
+            if !mark_center || !center_columns().contains(&column) {
+                number
+            } else if escape(CENTER_BACKGROUND).is_empty() {
+                format!("{}*", number)
+            } else {
+                shade_center(&number)
+            }
+        })
+        .collect();
+    render_row(&numbers)
+}
+
+// Player 1's share of the bar grows with `value`, reaching the end at
+// `range`. Wins clamp to the ends and a balanced position renders half full
+// without colour.
+fn render_gauge(value: i32, range: i32, width: usize) -> String {
+    let value = value.clamp(-range, range);
+    let filled = ((value + range) as usize * width + range as usize) / (2 * range as usize);
+    let bar = format!("[{}{}]", "#".repeat(filled), "-".repeat(width - filled));
+
+    match value.signum() {
+        1 => format!("{}{}{}", escape(RED), bar, escape(RESET)),
+        -1 => format!("{}{}{}", escape(ORANGE), bar, escape(RESET)),
+        _ => bar,
+    }
+}
+
+// Evaluations memoized by board fingerprint for the whole session, so
+// stepping back and forth through a game doesn't re-evaluate positions.
+// Each fingerprint hashes to one of a fixed number of slots and a new entry
+// always replaces the one there, so the cache never outgrows its size.
+struct EvalCache {
+    slots: Vec<Option<(String, i32)>>,
+}
+
+impl EvalCache {
+    fn new(capacity: usize) -> EvalCache {
+        EvalCache {
+            slots: vec![None; capacity],
+        }
+    }
+
+    fn evaluate(&mut self, game: &Game) -> i32 {
+        let fingerprint = game.board_fingerprint();
+        let mut hasher = DefaultHasher::new();
+        fingerprint.hash(&mut hasher);
+        let index = (hasher.finish() % self.slots.len() as u64) as usize;
+
+        // The slot may hold a different position that hashed to it.
+        match &self.slots[index] {
+            Some((key, eval)) if *key == fingerprint => *eval,
+            _ => {
+                let eval = game.evaluate();
+                self.slots[index] = Some((fingerprint, eval));
+                eval
+            }
+        }
+    }
+}
+
+fn announce_analysis(game: &Game, cache: &mut EvalCache) {
+    let (one_odd, one_even) = game.threat_parity(Player::One);
+    let (two_odd, two_even) = game.threat_parity(Player::Two);
+    let eval = cache.evaluate(game);
+
+    println!(
+        "{}Analysis: P1 has {} odd and {} even threats, P2 has {} odd and {} even threats (eval {:+}){}",
+        escape(ORANGE),
+        one_odd,
+        one_even,
+        two_odd,
+        two_even,
+        eval,
+        escape(RESET)
+    );
+    println!(
+        "P1 {} P2",
+        render_gauge(eval, EVAL_BAR_RANGE, EVAL_BAR_WIDTH)
+    );
+
+    let line = game.principal_variation(PV_DEPTH, &ThreatEvaluator);
+
+    if !line.is_empty() {
+        let mut end = game.clone();
+        for &column in &line {
+            end.play_move(column)
+                .expect("principal variation only plays legal moves");
+        }
+
+        let outcome = match end.outcome() {
+            GameOutcome::Win(winner) => format!(", {} wins", end.player_name(winner)),
+            GameOutcome::Draw => ", a draw".to_string(),
+            GameOutcome::Ongoing => String::new(),
+        };
+        let columns: Vec<String> = line
+            .iter()
+            .map(|column| column.to_user().to_string())
+            .collect();
+
+        println!(
+            "{}Analysis: expected line {}{}{}",
+            escape(ORANGE),
+            columns.join(" "),
+            outcome,
+            escape(RESET)
+        );
+    }
+
+    if game.is_zugzwang() {
+        println!(
+            "{}Analysis: {} is in zugzwang{}",
+            escape(ORANGE),
+            game.player_name(game.current_player),
+            escape(RESET)
+        );
+    }
+}
+
+// Plays a hypothetical continuation on a scratch copy of the game; the real
+// game is untouched and redrawn as it was once the branch is abandoned.
+fn explore_branch(game: &Game, cache: &mut EvalCache) -> Result<(), GameError> {
+    let mut branch = game.branch();
+
+    loop {
+        branch.display_board();
+        branch.last_error = None;
+        announce_analysis(&branch, cache);
+        println!(
+            "{}Analysis branch: enter a column to try it or 'X' to return to the game{}",
+            escape(ORANGE),
+            escape(RESET)
+        );
+
+        let line = read_stdin()?;
+
+        if line.trim().eq_ignore_ascii_case("x") {
+            game.display_board();
+            return Ok(());
+        }
+
+        let result = match parse_command(&line) {
+            Command::Drop(column) => branch.play_move(column).map_err(|err| err.to_string()),
+            Command::Invalid(message) => Err(message),
+            _ => Err("enter a column or 'X'".to_string()),
+        };
+
+        if let Err(message) = result {
+            branch.last_error = Some(message);
+        }
+    }
+}
+
+// Bundled challenge names with their difficulty scores, easiest first.
+fn challenges_by_difficulty() -> Vec<(&'static str, u32)> {
+    let mut challenges: Vec<(&str, u32)> = CHALLENGES
+        .iter()
+        .map(|&(name, diagram)| {
+            let game = Game::from_diagram(diagram).expect("bundled challenges are valid");
+            (name, game.difficulty_score())
+        })
+        .collect();
+
+    challenges.sort_by_key(|&(_, score)| score);
+    challenges
+}
+
+fn announce_difficulty(game: &Game) {
+    println!(
+        "{}Challenge difficulty: {}{}",
+        escape(ORANGE),
+        game.difficulty_score(),
+        escape(RESET)
+    );
+}
+
+// Counts a rejected input against `--max-retries`. Once the player runs out
+// the turn is lost: either the game is forfeited to the opponent or, with
+// `--on-retries=exit`, true is returned so the caller can quit.
+fn retry_exhausted(game: &mut Game, options: &Options, retries: &mut u32) -> bool {
+    *retries += 1;
+
+    match options.max_retries {
+        Some(max_retries) if *retries > max_retries => {
+            *retries = 0;
+
+            match options.retry_limit {
+                RetryLimit::Forfeit => {
+                    game.forfeit();
+                    game.display_board();
+                    println!(
+                        "{}Too many invalid inputs, the game is forfeited{}",
+                        escape(RED),
+                        escape(RESET)
+                    );
+                    false
+                }
+                RetryLimit::Exit => true,
+            }
+        }
+        _ => false,
+    }
+}
+
+// Shows where the disc would land and asks before dropping it. Anything but
+// 'y' from `answer` cancels, leaving the game as it was.
+fn confirm_move(
+    game: &Game,
+    column: Column,
+    answer: impl FnOnce() -> Result<String, GameError>,
+) -> Result<bool, GameError> {
+    let Some(landing_row) = game.preview(column) else {
+        return Ok(true);
+    };
+
+    let mut frame = game.clone();
+    frame.board[landing_row][column.0] = game.current_player as u8;
+    frame.display_board();
+    println!("Confirm column {}? (y/n)", column.to_user());
+
+    Ok(answer()?.trim().eq_ignore_ascii_case("y"))
+}
+
+// Under --column-lock the first press of a column locks it, pressing it
+// again gives it back to be played and pressing another moves the lock.
+fn lock_column(locked: &mut Option<Column>, column: Column) -> Option<Column> {
+    if *locked == Some(column) {
+        locked.take()
+    } else {
+        *locked = Some(column);
+        None
+    }
+}
+
+// Scripted players can't answer, so they turn every offer down.
+fn answer_draw_offer(game: &mut Game, options: &Options) -> Result<(), GameError> {
+    let accepted = !options.scripted(game.current_player) && {
+        println!("Opponent offers a draw — accept? (y/n)");
+        read_stdin()?.trim().eq_ignore_ascii_case("y")
+    };
+
+    if accepted {
+        game.accept_draw().expect("draw was offered");
+        game.display_board();
+        println!("{}Draw agreed{}", escape(ORANGE), escape(RESET));
+    } else {
+        game.decline_draw().expect("draw was offered");
+        println!(
+            "{}{} declines the draw{}",
+            escape(ORANGE),
+            game.player_name(game.current_player),
+            escape(RESET)
+        );
+    }
+
+    Ok(())
+}
+
+// Counts down to an automatic restart, one line per second. Any key typed
+// in the meantime stops the countdown and is handled as a menu choice.
+fn restart_countdown(secs: u64) -> Result<String, GameError> {
+    for remaining in (1..=secs).rev() {
+        println!("Restarting in {}s, press 'Q' to quit...", remaining);
+
+        if let Some(line) = read_stdin_timeout(Duration::from_secs(1)) {
+            return line;
+        }
+    }
+
+    Ok("R".to_string())
+}
+
+// Plays the finished game back one move per `delay`. '+' and '-' change the
+// speed, 'P' pauses or resumes, 'N' steps while paused and 'X' stops.
+// Reaching the last move pauses there, and stepping past it does nothing.
+fn play_back(game: &Game) -> Result<(), GameError> {
+    let total_moves = game.history.len();
+    let mut index = 0;
+    let mut delay = PLAYBACK_DELAY;
+    let mut paused = false;
+
+    loop {
+        game.position_at(index).display_board();
+
+        let state = if paused {
+            "paused".to_string()
+        } else {
+            format!("{}ms per move", delay.as_millis())
+        };
+        println!(
+            "{}Playback: move {} of {} ({}){}",
+            escape(ORANGE),
+            index,
+            total_moves,
+            state,
+            escape(RESET)
+        );
+        println!("'+'/'-' change the speed, 'P' pauses, 'N' steps and 'X' stops.");
+
+        let key = if paused {
+            Some(read_stdin()?)
+        } else {
+            read_stdin_timeout(delay).transpose()?
+        };
+
+        match key.as_deref().map(str::trim) {
+            None if index < total_moves => index += 1,
+            None => paused = true,
+            Some("+") => delay = (delay / 2).max(PLAYBACK_MIN_DELAY),
+            Some("-") => delay = (delay * 2).min(PLAYBACK_MAX_DELAY),
+            Some("P" | "p") => paused = !paused,
+            Some("N" | "n") if paused => index = (index + 1).min(total_moves),
+            Some("X" | "x") => {
+                game.display_board();
+                return Ok(());
+            }
+            Some(_) => {}
+        }
+
+        if index == total_moves {
+            paused = true;
+        }
+    }
+}
+
+fn run(
+    options: &Options,
+    sources: &mut [Box<dyn MoveSource>; 2],
+    mut game: Game,
+    mut observers: Vec<Box<dyn GameObserver>>,
+) -> Result<(), GameError> {
+    let mut replay_index: Option<usize> = None;
+    let mut eval_cache = EvalCache::new(options.tt_size.unwrap_or(EVAL_CACHE_CAPACITY));
+    let mut summarized = false;
+    let mut retries = 0;
+    let mut warned_blocks = Vec::new();
+    let mut warned_lost = false;
+    let mut challenge_pending = options.challenged();
+    let mut challenge_solutions = game.winning_moves(game.current_player);
+    let mut swap2 = Swap2::start(options.swap2, &game);
+    let mut held_names = hold_names(&mut game, swap2);
+    let mut seats_swapped = false;
+    let mut ply_evals = Vec::new();
+    let mut stats = SessionStats::default();
+    if options.postgame_analysis {
+        record_eval(&mut ply_evals, &game);
+    }
+    game.display_board();
+    if challenge_pending {
+        announce_difficulty(&game);
+    }
+    notify_reset(&mut observers, &game);
+
+    loop {
+        while !game.is_finished {
+            if let Swap2::Choosing { seat, can_place } = swap2 {
+                if verbosity() > Verbosity::Quiet {
+                    if !compact() {
+                        println!("\n");
+                    }
+                    println!("{}", seat_name(&held_names, seat).to_uppercase());
+                    println!(
+                        "Swap-two: 1 to play red, 2 to play yellow{}:",
+                        if can_place {
+                            ", 3 to place two more discs"
+                        } else {
+                            ""
+                        }
+                    );
+                }
+
+                match sources[seat].next_move(&game)? {
+                    Command::Drop(choice) => match swap2.choose(choice) {
+                        Some((Swap2::Done, swap_seats)) => {
+                            // Seats start out on their own colour, so taking
+                            // the other one swaps who plays which.
+                            if swap_seats {
+                                sources.swap(0, 1);
+                                held_names.swap(0, 1);
+                                seats_swapped = !seats_swapped;
+                            }
+
+                            let [p1_name, p2_name] = held_names.clone();
+                            game.meta.p1_name = p1_name;
+                            game.meta.p2_name = p2_name;
+                            swap2 = Swap2::Done;
+                            game.display_board();
+                        }
+                        Some((placing, _)) => {
+                            swap2 = placing;
+                            game.display_board();
+                        }
+                        None => game.display_error("choose one of the options".to_string()),
+                    },
+                    Command::Invalid(message) => game.display_error(message),
+                    _ => game.display_error("choose one of the options".to_string()),
+                }
+                continue;
+            }
+
+            if game.draw_offered() {
+                answer_draw_offer(&mut game, options)?;
+                continue;
+            }
+
+            // Nothing can be played, so asking for a move would only go
+            // round forever. Settle the game on the board as it stands.
+            if game.available_columns().is_empty() {
+                game.settle();
+                game.display_board();
+                continue;
+            }
+
+            if options.auto_forced {
+                if let Some(column) = game.forced_column() {
+                    game.play_move(column)
+                        .expect("available column is playable");
+                    game.display_board();
+                    if options.postgame_analysis {
+                        record_eval(&mut ply_evals, &game);
+                    }
+                    autosave(&mut game, options);
+                    notify_move(&mut observers, &game);
+                    println!(
+                        "{}Only column {} remains, auto-playing{}",
+                        escape(ORANGE),
+                        column.to_user(),
+                        escape(RESET)
+                    );
+                    continue;
+                }
+            }
+
+            // The search behind this is slow, so it only runs when asked for
+            // and stops once the side to move has been told.
+            if options.warn_forced_loss
+                && !warned_lost
+                && !matches!(swap2, Swap2::Placing { .. })
+                && game.is_lost()
+            {
+                warned_lost = true;
+                println!(
+                    "{}You are lost with best play — any move loses{}",
+                    escape(RED),
+                    escape(RESET)
+                );
+            }
+
+            if verbosity() > Verbosity::Quiet {
+                if !compact() {
+                    println!("\n");
+                }
+
+                let placing = if let Swap2::Placing { seat, .. } = swap2 {
+                    let colour = match game.current_player {
+                        Player::Two => "yellow",
+                        _ => "red",
+                    };
+
+                    println!("{}", seat_name(&held_names, seat).to_uppercase());
+                    println!("Swap-two: place a {} disc", colour);
+                    true
+                } else {
+                    if game.side_to_move() != Player::None && !compact() {
+                        println!("{}", game.player_name(game.side_to_move()).to_uppercase());
+                    }
+                    false
+                };
+
+                let prompt = match game.locked_column {
+                    Some(locked) => format!(
+                        "Enter {} again to drop, another column to move the lock or 'C' to cancel:",
+                        locked.to_user()
+                    ),
+                    None => format!(
+                        "Enter a column between 1 and {}:",
+                        game.board_view().width()
+                    ),
+                };
+
+                // The status line just above already names the side to
+                // move, so compact output carries on from it.
+                if compact() && !placing {
+                    print!("{} ", prompt);
+                    io::stdout().flush()?;
+                } else {
+                    println!("{}", prompt);
+                }
+            }
+
+            let source = match (swap2, game.side_to_move()) {
+                (Swap2::Placing { seat, .. }, _) => &mut sources[seat],
+                (_, Player::Two) => &mut sources[1],
+                _ => &mut sources[0],
+            };
+
+            // Only the attempt that produces a playable move is charged, so
+            // time spent on rejected input doesn't count against the clock.
+            let started = Instant::now();
+            let column = match source.next_move(&game)? {
+                Command::Drop(column) => column,
+                Command::Save => {
+                    save_game(&mut game, options.save.as_deref().unwrap_or(SAVE_PATH));
+                    continue;
+                }
+                Command::Dump => {
+                    print!("{}", game.debug_report());
+                    continue;
+                }
+                Command::Threats => {
+                    print!("{}", game.render_threat_overlay());
+                    continue;
+                }
+                Command::Describe => {
+                    print!("{}", game.describe());
+                    continue;
+                }
+                Command::Snapshot => {
+                    print!("{}", game.render_snapshot());
+                    continue;
+                }
+                Command::Cancel if game.locked_column.is_some() => {
+                    game.locked_column = None;
+                    game.display_board();
+                    continue;
+                }
+                Command::Cancel => {
+                    game.display_error("no column is locked".to_string());
+                    continue;
+                }
+                Command::OfferDraw if swap2 != Swap2::Done => {
+                    game.display_error("draw offers wait until the opening is settled".to_string());
+                    continue;
+                }
+                Command::OfferDraw => {
+                    match game.offer_draw() {
+                        Ok(()) => println!(
+                            "{}{} offers a draw{}",
+                            escape(ORANGE),
+                            game.player_name(game.current_player),
+                            escape(RESET)
+                        ),
+                        Err(err) => game.display_error(err.to_string()),
+                    }
+                    continue;
+                }
+                Command::Undo | Command::Redo if swap2 != Swap2::Done => {
+                    game.display_error("takebacks wait until the opening is settled".to_string());
+                    continue;
+                }
+                Command::Undo | Command::Redo if options.rated => {
+                    game.display_error("takebacks are disabled in rated games".to_string());
+                    continue;
+                }
+                command @ (Command::Undo | Command::Redo) => {
+                    let result = match command {
+                        Command::Undo => game.undo_turn(options.takeback_plies(&game)),
+                        _ => game.redo(),
+                    };
+
+                    match result {
+                        Ok(()) => {
+                            game.display_board();
+                            if options.postgame_analysis {
+                                record_eval(&mut ply_evals, &game);
+                            }
+                            autosave(&mut game, options);
+                        }
+                        Err(err) => game.display_error(err.to_string()),
+                    }
+                    continue;
+                }
+                Command::Branch if options.analyze => {
+                    explore_branch(&game, &mut eval_cache)?;
+                    continue;
+                }
+                Command::Branch => {
+                    game.display_error("analysis branches need --analyze".to_string());
+                    continue;
+                }
+                Command::Invalid(message) => {
+                    game.display_error(message);
+
+                    if retry_exhausted(&mut game, options, &mut retries) {
+                        println!("Too many invalid inputs, quitting...");
+                        return Ok(());
+                    }
+                    continue;
+                }
+            };
+
+            if options.column_lock
+                && !options.scripted(game.current_player)
+                && game.rules.validate(&game, column).is_ok()
+                && lock_column(&mut game.locked_column, column).is_none()
+            {
+                game.display_board();
+                continue;
+            }
+
+            if options.safe && game.refuses_move(column) {
+                game.display_error("that move loses, pick another".to_string());
+
+                if retry_exhausted(&mut game, options, &mut retries) {
+                    println!("Too many invalid inputs, quitting...");
+                    return Ok(());
+                }
+                continue;
+            }
+
+            if options.confirm
+                && !options.scripted(game.current_player)
+                && game.rules.validate(&game, column).is_ok()
+                && !confirm_move(&game, column, read_stdin)?
+            {
+                game.display_board();
+                continue;
+            }
+
+            // Only a single threat can be blocked, and each is flagged once.
+            let missed_block = if options.warn_blocks {
+                game.missed_block(column, &warned_blocks)
+            } else {
+                None
+            };
+
+            let elapsed = started.elapsed();
+            let mover = game.current_player;
+
+            if game.rules.validate(&game, column).is_ok()
+                && game
+                    .clock
+                    .as_mut()
+                    .is_some_and(|clock| !clock.charge(mover, elapsed))
+            {
+                game.forfeit();
+                game.display_board();
+                println!(
+                    "{}{} ran out of time{}",
+                    escape(RED),
+                    game.player_name(mover),
+                    escape(RESET)
+                );
+                continue;
+            }
+
+            if options.animate {
+                if let Some(landing_row) = game.preview(column) {
+                    game.animate_drop(column, landing_row);
+                }
+            }
+
+            match game.play_move(column) {
+                Ok(_) => {
+                    retries = 0;
+                    swap2 = swap2.placed();
+                    game.display_board();
+                    if let Some(&landed) = game.history.last().filter(|&&landed| landed != column) {
+                        println!(
+                            "{}The disc bounced into column {}{}",
+                            escape(ORANGE),
+                            landed.to_user(),
+                            escape(RESET)
+                        );
+                    }
+                    if verbosity() >= Verbosity::Verbose {
+                        report_move(&game, column, elapsed);
+                    }
+                    if game.clock.is_some() {
+                        stats.move_time += elapsed;
+                        stats.timed_moves += 1;
+                    }
+                    if options.postgame_analysis {
+                        record_eval(&mut ply_evals, &game);
+                    }
+                    autosave(&mut game, options);
+                    notify_move(&mut observers, &game);
+
+                    if let Some(block) = missed_block {
+                        warned_blocks.push(block);
+                        println!(
+                            "{}⚠ you had to block column {}{}",
+                            escape(RED),
+                            block.to_user(),
+                            escape(RESET)
+                        );
+                    }
+
+                    if options.coach {
+                        announce_patterns(&game);
+                    }
+
+                    if options.analyze {
+                        announce_analysis(&game, &mut eval_cache);
+                    }
+
+                    if challenge_pending {
+                        challenge_pending = false;
+
+                        if challenge_solutions.contains(&column) {
+                            println!(
+                                "{}You found the winning move!{}",
+                                escape(ORANGE),
+                                escape(RESET)
+                            );
+                        } else if challenge_solutions.is_empty() {
+                            println!(
+                                "{}This challenge has no winning move under the current rules.{}",
+                                escape(RED),
+                                escape(RESET)
+                            );
+                        } else {
+                            let columns: Vec<String> = challenge_solutions
+                                .iter()
+                                .map(|column| column.to_user().to_string())
+                                .collect();
+                            println!(
+                                "{}Not quite, the winning move was column {}.{}",
+                                escape(RED),
+                                columns.join(" or "),
+                                escape(RESET)
+                            );
+                        }
+                    }
+                }
+                Err(err) => {
+                    game.display_error(err.to_string());
+
+                    if retry_exhausted(&mut game, options, &mut retries) {
+                        println!("Too many invalid inputs, quitting...");
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        let just_finished = !summarized;
+
+        if !summarized {
+            summarized = true;
+            stats.record(&game);
+
+            notify_end(&mut observers, &game);
+
+            if options.annotate {
+                print!("{}", game.annotate_game());
+
+                match game.win_efficiency() {
+                    Some(0) => println!("Win efficiency: took the fastest forced win"),
+                    Some(extra) if extra > 0 => println!(
+                        "Win efficiency: {} move(s) more than the fastest forced win",
+                        extra
+                    ),
+                    Some(extra) => println!(
+                        "Win efficiency: won {} move(s) sooner than could be forced",
+                        -extra
+                    ),
+                    None => {}
+                }
+
+                let evals = game.searched_evals(&[]);
+                for moment in game.describe_key_moments(&evals, KEY_MOMENT_SWING) {
+                    println!("{}{}{}", escape(ORANGE), moment, escape(RESET));
+                }
+            }
+
+            if options.postgame_analysis {
+                print!("{}", postgame_report(&game, &ply_evals, options.annotate));
+            }
+
+            if options.strict_report {
+                for line in game.strict_report() {
+                    println!("{}{}{}", escape(RED), line, escape(RESET));
+                }
+            }
+
+            if options.think_map {
+                print!("{}", game.render_think_map());
+            }
+
+            if options.timeline {
+                for event in game.threat_timeline() {
+                    println!("{}{}{}", escape(ORANGE), event, escape(RESET));
+                }
+            }
+        }
+
+        // Holds the final board for a moment before the menu. A key pressed
+        // in the meantime cuts the pause short and is taken as the choice.
+        let paused_input = match options.win_pause(just_finished) {
+            Some(pause) => read_stdin_timeout(pause).transpose()?,
+            None => None,
+        };
+
+        write_menu(&mut io::stdout(), &game, verbosity(), just_finished)?;
+
+        let user_input = match (paused_input, options.auto_restart) {
+            (Some(line), _) => line,
+            (None, Some(secs)) if just_finished => restart_countdown(secs)?,
+            _ => read_stdin()?,
+        };
+
+        match user_input.trim() {
+            input @ ("[" | "]") => {
+                let total_moves = game.history.len();
+                let current = replay_index.unwrap_or(total_moves);
+                let index = if input == "[" {
+                    current.saturating_sub(1)
+                } else {
+                    (current + 1).min(total_moves)
+                };
+
+                replay_index = Some(index);
+                show_replay(&game, index, options, &mut eval_cache);
+            }
+            "B" | "b" => {
+                let evals = game.searched_evals(&ply_evals);
+
+                match game.first_blunder(&evals, KEY_MOMENT_SWING) {
+                    Some(move_index) => {
+                        replay_index = Some(move_index + 1);
+                        show_replay(&game, move_index + 1, options, &mut eval_cache);
+                        println!(
+                            "{}Move {} was the first blunder{}",
+                            escape(ORANGE),
+                            move_index + 1,
+                            escape(RESET)
+                        );
+                    }
+                    None => println!(
+                        "{}No decisive blunder found{}",
+                        escape(ORANGE),
+                        escape(RESET)
+                    ),
+                }
+            }
+            "P" | "p" => play_back(&game)?,
+            "S" | "s" => save_game(&mut game, options.save.as_deref().unwrap_or(SAVE_PATH)),
+            "D" | "d" => print!("{}", game.debug_report()),
+            "R" | "r" => {
+                game = options.new_game();
+                if seats_swapped {
+                    sources.swap(0, 1);
+                    seats_swapped = false;
+                }
+                swap2 = Swap2::start(options.swap2, &game);
+                held_names = hold_names(&mut game, swap2);
+                notify_reset(&mut observers, &game);
+                replay_index = None;
+                summarized = false;
+                ply_evals.clear();
+                if options.postgame_analysis {
+                    record_eval(&mut ply_evals, &game);
+                }
+                warned_blocks.clear();
+                warned_lost = false;
+                retries = 0;
+                challenge_pending = options.challenged();
+                challenge_solutions = game.winning_moves(game.current_player);
+                game.display_board();
+                if challenge_pending {
+                    announce_difficulty(&game);
+                }
+            }
+            "Q" | "q" => {
+                if verbosity() > Verbosity::Quiet {
+                    if options.stats {
+                        for line in stats.summary(&game) {
+                            println!("{}{}{}", escape(ORANGE), line, escape(RESET));
+                        }
+                    }
+                    println!("Quitting...");
+                }
+                return Ok(());
+            }
+            _ => game.display_error("invalid input".to_string()),
+        }
+    }
+}
+
+// Shows the position after `index` moves of the finished game.
+fn show_replay(game: &Game, index: usize, options: &Options, eval_cache: &mut EvalCache) {
+    let position = game.position_at(index);
+    position.display_board();
+
+    if options.analyze {
+        announce_analysis(&position, eval_cache);
+    }
+
+    println!(
+        "{}Replay: move {} of {}{}",
+        escape(ORANGE),
+        index,
+        game.history.len(),
+        escape(RESET)
+    );
+}
+
+// Totals over the games finished this session, for --stats. Results are
+// kept by colour, and move times only build up in games with a clock.
+#[derive(Default)]
+struct SessionStats {
+    games: u32,
+    wins: [u32; 2],
+    draws: u32,
+    moves: usize,
+    longest: usize,
+    move_time: Duration,
+    timed_moves: u32,
+}
+
+impl SessionStats {
+    fn record(&mut self, game: &Game) {
+        self.games += 1;
+        self.moves += game.history.len();
+        self.longest = self.longest.max(game.history.len());
+
+        match game.winner {
+            Player::One => self.wins[0] += 1,
+            Player::Two => self.wins[1] += 1,
+            Player::None => self.draws += 1,
+        }
+    }
+
+    // Names are taken from `game`, the one on the board when quitting.
+    fn summary(&self, game: &Game) -> Vec<String> {
+        let plural = |count: u32, word: &str| match count {
+            1 => format!("1 {}", word),
+            _ => format!("{} {}s", count, word),
+        };
+        let mut lines = vec![format!("Session: {} played", plural(self.games, "game"))];
+
+        for (seat, player) in [Player::One, Player::Two].into_iter().enumerate() {
+            let losses = self.wins[1 - seat];
+
+            lines.push(format!(
+                "{}: {}, {} and {}",
+                game.player_name(player),
+                plural(self.wins[seat], "win"),
+                match losses {
+                    1 => "1 loss".to_string(),
+                    _ => format!("{} losses", losses),
+                },
+                plural(self.draws, "draw")
+            ));
+        }
+
+        if self.games > 0 {
+            lines.push(format!(
+                "Average moves per game: {:.1}",
+                self.moves as f64 / self.games as f64
+            ));
+            lines.push(format!("Longest game: {} moves", self.longest));
+        }
+
+        if self.timed_moves > 0 {
+            lines.push(format!(
+                "Average move time: {:.1}s",
+                self.move_time.as_secs_f64() / self.timed_moves as f64
+            ));
+        }
+
+        lines
+    }
+}
+
+// Which game to play. Dots and Boxes runs on its own board and loop, so
+// none of the Connect 4 options apply to it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum GameMode {
+    #[default]
+    ConnectFour,
+    Dots,
+}
+
+// xorshift64*, seeded from the clock for training positions or from a fixed
+// seed where the same positions must come up every time.
+struct Rng(u64);
+
+impl Rng {
+    fn from_seed(seed: u64) -> Rng {
+        Rng(seed | 1)
+    }
+
+    fn from_clock() -> Rng {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or(0);
+
+        Rng(nanos | 1)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) % n as u64) as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TrainPattern {
+    Win,
+    Block,
+}
+
+impl TrainPattern {
+    // The one correct move in `game`, or nothing if the position doesn't set
+    // the pattern: a single win to find, or a single threat to block while
+    // the mover has no win of their own.
+    fn answer(self, game: &Game) -> Option<Column> {
+        let columns = match self {
+            TrainPattern::Win => game.winning_moves(game.current_player),
+            TrainPattern::Block => game.forced_blocks(),
+        };
+
+        match columns[..] {
+            [column] => Some(column),
+            _ => None,
+        }
+    }
+
+    fn prompt(self) -> &'static str {
+        match self {
+            TrainPattern::Win => "Find the winning move",
+            TrainPattern::Block => "Find the move that stops the threat",
+        }
+    }
+}
+
+// Everyone playing on the same day gets the same puzzle, since the random
+// playout that finds it is seeded from the date.
+fn daily_puzzle(options: &Options, date: &str) -> Result<Game, GameError> {
+    let mut rng = Rng::from_seed(fnv1a(date.as_bytes()));
+    let (puzzle, _) = training_position(options, TrainPattern::Win, &mut rng)?;
+
+    Ok(puzzle)
+}
+
+// Plays random moves from a new game until the position sets `pattern`,
+// starting over when a playout ends or runs long. Every position is reached
+// through `play_move`, so it is legal under the configured rules. Some rules
+// never set the pattern, so the playouts are capped.
+fn training_position(
+    options: &Options,
+    pattern: TrainPattern,
+    rng: &mut Rng,
+) -> Result<(Game, Column), GameError> {
+    for _ in 0..TRAIN_MAX_PLAYOUTS {
+        let mut game = options.new_game();
+
+        while !game.is_finished && game.current_move < TRAIN_MAX_PLIES {
+            if game.current_move >= TRAIN_MIN_PLIES {
+                if let Some(answer) = pattern.answer(&game) {
+                    return Ok((game, answer));
+                }
+            }
+
+            let columns = game.available_columns();
+            let column = columns[rng.below(columns.len())];
+            game.play_move(column)
+                .expect("available column is playable");
+        }
+    }
+
+    Err(GameError::NoTrainingPosition(TRAIN_MAX_PLAYOUTS))
+}
+
+// Sets up one position after another and grades each answer, keeping a
+// streak of correct ones. The verdict is shown under the next position.
+fn run_training(options: &Options, pattern: TrainPattern) -> Result<(), GameError> {
+    let mut rng = Rng::from_clock();
+    let mut streak = 0;
+    let mut verdict: Option<String> = None;
+
+    loop {
+        let (mut game, answer) = training_position(options, pattern, &mut rng)?;
+        game.display_board();
+
+        loop {
+            if let Some(verdict) = verdict.take() {
+                println!("{}{}{}", escape(ORANGE), verdict, escape(RESET));
+            }
+            println!("Streak: {}", streak);
+            println!(
+                "{} for {}, or 'Q' to stop:",
+                pattern.prompt(),
+                game.player_name(game.current_player)
+            );
+
+            let line = read_stdin()?;
+
+            if line.trim().eq_ignore_ascii_case("q") {
+                println!("Final streak: {}", streak);
+                return Ok(());
+            }
+
+            match parse_command(&line) {
+                Command::Drop(column) if column == answer => {
+                    streak += 1;
+                    verdict = Some(format!("Correct, column {}!", answer.to_user()));
+                }
+                Command::Drop(_) => {
+                    streak = 0;
+                    verdict = Some(format!(
+                        "Not quite, the answer was column {}. Streak reset.",
+                        answer.to_user()
+                    ));
+                }
+                Command::Invalid(message) => {
+                    game.display_error(message);
+                    continue;
+                }
+                _ => {
+                    game.display_error("enter a column or 'Q'".to_string());
+                    continue;
+                }
+            }
+            break;
+        }
+    }
+}
+
+// The prompt once a game is over. Quiet output leaves only the result,
+// printed the once, for scripts to read.
+fn write_menu<W: Write>(
+    out: &mut W,
+    game: &Game,
+    level: Verbosity,
+    just_finished: bool,
+) -> io::Result<()> {
+    if level > Verbosity::Quiet {
+        writeln!(
+            out,
+            "Press '[' or ']' to step through the game, 'B' to jump to the first blunder, 'P' to play it back or 'S' to save it."
+        )?;
+        writeln!(
+            out,
+            "Press 'R' to restart or 'Q' to quit the game ('D' dumps debug state)."
+        )?;
+    } else if just_finished {
+        writeln!(out, "{}", game.result())?;
+    }
+
+    Ok(())
+}
+
+// Moves after which the evaluation swung by more than `threshold`, given
+// the evaluation of every position in order, with the swing from Player 1's
+// side.
+fn key_moments(evals: &[i32], threshold: i32) -> Vec<(usize, i32)> {
+    evals
+        .windows(2)
+        .enumerate()
+        .map(|(move_index, pair)| (move_index, pair[1] - pair[0]))
+        .filter(|&(_, swing)| swing.abs() > threshold)
+        .collect()
+}
+
+// Stores the evaluation of the position `game` is now in, by ply, for the
+// post-game report. Takebacks just overwrite the plies they revisit.
+fn record_eval(evals: &mut Vec<Option<i32>>, game: &Game) {
+    let ply = game.history.len();
+
+    if evals.len() <= ply {
+        evals.resize(ply + 1, None);
+    }
+    evals[ply] = Some(game.searched_eval());
+}
+
+// Everything --postgame-analysis held back during play. The annotated moves
+// are left out when --annotate has printed them already.
+fn postgame_report(game: &Game, recorded: &[Option<i32>], annotated: bool) -> String {
+    let evals = game.searched_evals(recorded);
+    let evals_text: Vec<String> = evals.iter().map(|eval| format!("{:+}", eval)).collect();
+    let mut report = format!("{}Post-game analysis:{}\n", escape(ORANGE), escape(RESET));
+
+    if !annotated {
+        report.push_str(&game.annotate_game());
+    }
+    report.push_str(&format!("Evaluations: {}\n", evals_text.join(" ")));
+    for moment in game.describe_key_moments(&evals, KEY_MOMENT_SWING) {
+        report.push_str(&format!("{}{}{}\n", escape(ORANGE), moment, escape(RESET)));
+    }
+
+    report
+}
+
+// Verbose output after each move: how long it took and the searched
+// evaluation, plus the positions the search tried when very verbose.
+fn report_move(game: &Game, column: Column, elapsed: Duration) {
+    let nodes = SEARCH_NODES.load(Ordering::Relaxed);
+    let mut line = format!(
+        "Move {}: column {} in {:.1}s, eval {:+}",
+        game.current_move,
+        column.to_user(),
+        elapsed.as_secs_f64(),
+        game.searched_eval()
+    );
+
+    if verbosity() >= Verbosity::VeryVerbose {
+        let searched = SEARCH_NODES.load(Ordering::Relaxed) - nodes;
+        line.push_str(&format!(", {} positions searched", searched));
+    }
+
+    println!("{}{}{}", escape(ORANGE), line, escape(RESET));
+}
+
+// The command-line game behind the binary: parses the arguments and plays
+// whatever they ask for.
+pub fn run_cli() {
+    let mut options = Options::parse(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    });
+
+    ANSI.set(ansi_for(&io::stdout(), options.colour))
+        .expect("output mode is set before anything is printed");
+    VERBOSITY
+        .set(options.verbosity)
+        .expect("verbosity is set before anything is printed");
+    COMPACT
+        .set(options.display.compact)
+        .expect("compact output is set before anything is printed");
+
+    let mut sources: [Box<dyn MoveSource>; 2] = [Box::new(StdinSource), Box::new(StdinSource)];
+
+    for (source, script) in sources.iter_mut().zip(&options.scripts) {
+        if let Some(path) = script {
+            match ScriptSource::from_file(path) {
+                Ok(script) => *source = Box::new(script),
+                Err(err) => {
+                    eprintln!("Error: failed to read {}: {}", path, err);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+
+    if options.drill {
+        match load_game(AUTOSAVE_PATH) {
+            Ok(logged) => {
+                sources[1] = Box::new(DrillSource {
+                    moves: logged.history,
+                    diverged: false,
+                })
+            }
+            Err(err) => {
+                eprintln!("Error: failed to load {}: {}", AUTOSAVE_PATH, err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(path) = &options.lines {
+        match LineTrie::from_file(path) {
+            Ok(lines) => {
+                sources[1] = Box::new(LinesSource {
+                    lines,
+                    left_book: false,
+                })
+            }
+            Err(err) => {
+                eprintln!("Error: failed to read {}: {}", path, err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(path) = &options.verify_log {
+        match std::fs::read_to_string(path).map(|text| verify_log(&text)) {
+            Ok(Ok(hash)) => println!("{} verifies, certificate {:016x}", path, hash),
+            Ok(Err(line)) => {
+                eprintln!("Error: {} line {} doesn't match its hash", path, line);
+                std::process::exit(1);
+            }
+            Err(err) => {
+                eprintln!("Error: failed to read {}: {}", path, err);
+                std::process::exit(1);
+            }
+        }
+
+        return;
+    }
+
+    if options.check {
+        let results = self_check();
+
+        for (check, result) in &results {
+            match result {
+                Ok(()) => println!("PASS {}", check),
+                Err(err) => println!("FAIL {}: {}", check, err),
+            }
+        }
+
+        if results.iter().any(|(_, result)| result.is_err()) {
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    if let Some((first, second)) = &options.diff_games {
+        if let Err(err) = diff_games(first, second, &options) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    if let Some(dir) = &options.bench_replay {
+        if let Err(err) = bench_replay(Path::new(dir), &options) {
+            eprintln!("Error: failed to read {}: {}", dir, err);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    // The TUI reads keys itself, so its players are named with --p1-name and
+    // --p2-name instead.
+    if options.export_frames.is_none()
+        && options.export_sgf.is_none()
+        && options.perft.is_none()
+        && !options.tui
+        && !options.protocol
+        && options.train.is_none()
+        && io::stdin().is_terminal()
+    {
+        match prompt_names(&mut options) {
+            Ok(()) => {}
+            Err(GameError::InputClosed) => {
+                println!("Input closed, quitting...");
+                return;
+            }
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if options.mode == GameMode::Dots {
+        match dots::run_dots(&options) {
+            Ok(()) => {}
+            Err(GameError::InputClosed) => println!("Input closed, quitting..."),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+
+        return;
+    }
+
+    if let Some(pattern) = options.train {
+        match run_training(&options, pattern) {
+            Ok(()) => {}
+            Err(GameError::InputClosed) => println!("Input closed, quitting..."),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+
+        return;
+    }
+
+    if options.daily {
+        let puzzle = daily_puzzle(&options, &today()).unwrap_or_else(|err| {
+            eprintln!("Error: no daily puzzle: {}", err);
+            std::process::exit(1);
+        });
+
+        options.start_board = Some(puzzle.board);
+    }
+
+    let game = match &options.load {
+        Some(path) => load_game(path)
+            .and_then(|game| options.configure(game))
+            .unwrap_or_else(|err| {
+                eprintln!("Error: failed to load {}: {}", path, err);
+                std::process::exit(1);
+            }),
+        None if options.resume => {
+            match load_game(AUTOSAVE_PATH).and_then(|game| options.configure(game)) {
+                Ok(game) => game,
+                Err(err) => {
+                    eprintln!(
+                        "Warning: couldn't resume from {}: {}, starting a new game",
+                        AUTOSAVE_PATH, err
+                    );
+                    options.new_game()
+                }
+            }
+        }
+        None => options.new_game(),
+    };
+
+    if let Some(dir) = &options.export_frames {
+        match game.render_frames_to_dir(Path::new(dir)) {
+            Ok(()) => println!("Wrote {} frames to {}", game.history.len() + 1, dir),
+            Err(err) => {
+                eprintln!("Error: failed to write frames to {}: {}", dir, err);
+                std::process::exit(1);
+            }
+        }
+
+        return;
+    }
+
+    if let Some(path) = &options.export_sgf {
+        match std::fs::write(path, game.to_sgf()) {
+            Ok(()) => println!("Wrote the game record to {}", path),
+            Err(err) => {
+                eprintln!("Error: failed to write {}: {}", path, err);
+                std::process::exit(1);
+            }
+        }
+
+        return;
+    }
+
+    if options.protocol {
+        if let Err(err) = run_protocol(&options, game, io::stdin().lock(), io::stdout()) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    if let Some(depth) = options.perft {
+        for depth in 1..=depth {
+            println!("perft {}: {}", depth, game.count_leaves(depth));
+        }
+
+        return;
+    }
+
+    let mut observers: Vec<Box<dyn GameObserver>> = Vec::new();
+
+    if options.rated {
+        match RatedLog::open(RATED_LOG_PATH) {
+            Ok(log) => observers.push(Box::new(log)),
+            Err(err) => {
+                eprintln!("Error: failed to open {}: {}", RATED_LOG_PATH, err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let tui_guard = options.tui.then(|| {
+        if !io::stdin().is_terminal() {
+            eprintln!("Error: --tui needs a terminal");
+            std::process::exit(1);
+        }
+
+        TuiGuard::enter().unwrap_or_else(|err| {
+            eprintln!("Error: failed to set up the terminal: {}", err);
+            std::process::exit(1);
+        })
+    });
+
+    let result = run(&options, &mut sources, game, observers);
+    drop(tui_guard);
+
+    match result {
+        Ok(()) => {}
+        Err(GameError::InputClosed) if verbosity() == Verbosity::Quiet => {}
+        Err(GameError::InputClosed) => println!("Input closed, quitting..."),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Renders for the comparisons below come out without colour codes,
+    // whatever the terminal running the tests.
+    fn plain() {
+        let _ = ANSI.set(false);
+    }
+
+    fn diagram(text: &str) -> Game {
+        Game::from_diagram(text).expect("test diagram is a legal position")
+    }
+
+    fn play(moves: &[usize]) -> Game {
+        let mut game = Game::default();
+
+        for &number in moves {
+            game.play_move(Column::from_user(number).unwrap()).unwrap();
+        }
+
+        game
+    }
+
+    #[test]
+    fn longest_run_counts_runs_along_the_bottom_and_side_edges() {
+        let game = diagram(
+            ".......
+             .......
+             .......
+             ......O
+             ......O
+             XXX...O",
+        );
+
+        assert_eq!(game.longest_run(Player::One), 3);
+        assert_eq!(game.longest_run(Player::Two), 3);
+    }
+
+    #[test]
+    fn longest_run_counts_a_run_ending_in_the_top_row() {
+        let game = diagram(
+            "X......
+             X......
+             X......
+             O......
+             X......
+             OOO....",
+        );
+
+        assert_eq!(game.longest_run(Player::One), 3);
+        assert_eq!(game.longest_run(Player::Two), 3);
+    }
+
+    #[test]
+    fn longest_run_is_zero_on_an_empty_board() {
+        assert_eq!(Game::default().longest_run(Player::One), 0);
+        assert_eq!(Game::default().longest_run(Player::Two), 0);
+    }
+
+    #[test]
+    fn empty_board_is_symmetric() {
+        assert!(Game::default().is_symmetric());
+    }
+
+    #[test]
+    fn centre_drop_keeps_the_board_symmetric() {
+        assert!(play(&[4]).is_symmetric());
+        assert!(play(&[4, 4]).is_symmetric());
+    }
+
+    #[test]
+    fn off_centre_drop_breaks_symmetry() {
+        assert!(!play(&[3]).is_symmetric());
+        assert!(!play(&[4, 5]).is_symmetric());
+        assert!(!play(&[3, 5]).is_symmetric());
+    }
+
+    // Timing only, so it is left out of normal runs. Run it with
+    // `cargo test --release -- --ignored --nocapture render_benchmark`.
+    #[test]
+    #[ignore]
+    fn render_benchmark() {
+        const FRAMES: u32 = 20_000;
+        let game = play(&DRAWN_GAME);
+        let mut out = Vec::new();
+
+        let started = Instant::now();
+        for _ in 0..FRAMES {
+            out.clear();
+            game.write_board(&mut out, Verbosity::Normal).unwrap();
+        }
+        let elapsed = started.elapsed();
+
+        println!(
+            "{} frames of a full board in {:.2?}, {:.1}µs per frame",
+            FRAMES,
+            elapsed,
+            elapsed.as_secs_f64() * 1e6 / f64::from(FRAMES)
+        );
+        assert!(!out.is_empty());
+    }
+
+    fn binary_save(game: &Game) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        game.save_binary(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn binary_save_round_trips() {
+        let mut game = play(&[4, 4, 3, 5, 2, 6]);
+        game.meta.p1_name = Some("Alice".to_string());
+
+        let loaded = Game::load_binary(&mut &binary_save(&game)[..]).unwrap();
+
+        assert!(loaded == game);
+        assert_eq!(loaded.history, game.history);
+        assert_eq!(loaded.meta.p1_name.as_deref(), Some("Alice"));
+    }
+
+    #[test]
+    fn binary_save_round_trips_a_starting_position() {
+        let game = diagram(
+            ".......
+             .......
+             .......
+             .......
+             .......
+             ..XO...",
+        );
+
+        let loaded = Game::load_binary(&mut &binary_save(&game)[..]).unwrap();
+
+        assert_eq!(loaded.start_board, game.start_board);
+        assert!(loaded == game);
+    }
+
+    #[test]
+    fn binary_load_rejects_unknown_versions() {
+        let mut bytes = binary_save(&play(&[4]));
+        bytes[SAVE_MAGIC.len()] = SAVE_VERSION + 1;
+
+        assert!(matches!(
+            Game::load_binary(&mut &bytes[..]),
+            Err(LoadError::UnsupportedVersion(version)) if version == SAVE_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn binary_load_rejects_truncated_files() {
+        let bytes = binary_save(&play(&[4, 4, 3]));
+
+        for length in 0..bytes.len() {
+            assert!(
+                matches!(
+                    Game::load_binary(&mut &bytes[..length]),
+                    Err(LoadError::Truncated)
+                ),
+                "a save cut to {} bytes loaded",
+                length
+            );
+        }
+    }
+
+    // The known perft counts for the standard board, where nobody can win
+    // yet so every ply multiplies by seven.
+    #[test]
+    fn leaf_counts_from_the_empty_board() {
+        let game = Game::default();
+        let counts: Vec<u64> = (0..=6).map(|depth| game.count_leaves(depth)).collect();
+
+        assert_eq!(counts, [1, 7, 49, 343, 2401, 16807, 117649]);
+    }
+
+    // Player 1 wins at once in column 1, which counts as one leaf, and the
+    // other six moves each leave Player 2 seven replies.
+    #[test]
+    fn leaf_counts_stop_at_a_win() {
+        assert_eq!(play(&[1, 2, 1, 2, 1, 2]).count_leaves(2), 1 + 6 * 7);
+    }
+
+    // Fills all 42 cells without anyone making four.
+    const DRAWN_GAME: [usize; 42] = [
+        1, 2, 1, 2, 1, 2, 2, 1, 2, 1, 2, 1, 3, 4, 3, 4, 3, 4, 4, 3, 4, 3, 4, 3, 5, 6, 5, 6, 5, 6,
+        6, 5, 6, 5, 6, 5, 7, 7, 7, 7, 7, 7,
+    ];
+
+    #[test]
+    fn full_board_ends_the_game_and_offers_no_moves() {
+        let mut game = play(&DRAWN_GAME);
+
+        assert!(game.is_finished);
+        assert_eq!(game.winner, Player::None);
+        assert!(game.available_columns().is_empty());
+        assert_eq!(game.move_iter().count(), 0);
+        assert!(game
+            .principal_variation(PV_DEPTH, &ThreatEvaluator)
+            .is_empty());
+        assert!(!game.forces_win(Player::One, FORCED_WIN_DEPTH));
+        assert!(matches!(
+            game.play_move(Column(3)),
+            Err(MoveError::GameFinished)
+        ));
+    }
+
+    #[test]
+    fn parses_column_numbers() {
+        assert!(matches!(parse_command("4"), Command::Drop(Column(3))));
+        assert!(matches!(parse_command(" 7 \n"), Command::Drop(Column(6))));
+        assert!(matches!(parse_command("04"), Command::Drop(Column(3))));
+        assert!(matches!(parse_command("007"), Command::Drop(Column(6))));
+    }
+
+    #[test]
+    fn rejects_out_of_range_columns_with_the_real_range() {
+        for input in ["0", "8", "10", "12", "99"] {
+            match parse_command(input) {
+                Command::Invalid(message) => {
+                    assert_eq!(message, "column must be between 1 and 7", "input {}", input)
+                }
+                _ => panic!("'{}' parsed as a move", input),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_input_that_is_not_a_number() {
+        for input in ["", "-3", "4a", "four", "1 2"] {
+            assert!(
+                matches!(parse_command(input), Command::Invalid(_)),
+                "'{}' parsed as a command",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn binary_save_refuses_a_header_too_long_to_store() {
+        let mut game = play(&[4]);
+        game.meta.note = Some("x".repeat(usize::from(u16::MAX) + 1));
+        let mut bytes = Vec::new();
+
+        let err = game.save_binary(&mut bytes).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn cloned_game_can_be_searched_on_another_thread() {
+        let mut game = play(&[4, 4, 3, 3]);
+        let expected = game.forces_win(Player::One, FORCED_WIN_DEPTH);
+        let snapshot = game.clone();
+
+        let search = thread::spawn(move || {
+            (
+                snapshot.forces_win(Player::One, FORCED_WIN_DEPTH),
+                snapshot.principal_variation(PV_DEPTH, &ThreatEvaluator),
+            )
+        });
+
+        // The original carries on while the clone is searched.
+        game.play_move(Column(4)).unwrap();
+        game.play_move(Column(0)).unwrap();
+
+        let (found, line) = search.join().unwrap();
+        assert_eq!(found, expected);
+        assert!(!line.is_empty());
+        assert_eq!(game.history.len(), 6);
+    }
+
+    #[test]
+    fn grow_up_shows_one_row_of_an_empty_board() {
+        plain();
+        let mut game = Game::default();
+        game.display.grow_up = true;
+
+        assert_eq!(
+            game.render_board(),
+            "--------------------
+CONNECT 4 (Move 0, opening, 0% full, symmetric position)
+P1 longest: 0 | P2 longest: 0
+--------------------
+1  2  3  4  5  6  7
+⚫ ⚫ ⚫ ⚫ ⚫ ⚫ ⚫
+--------------------
+"
+        );
+    }
+
+    #[test]
+    fn grow_up_shows_rows_from_the_highest_disc_down() {
+        plain();
+        let mut game = play(&[4, 4, 3]);
+        game.display.grow_up = true;
+
+        assert_eq!(
+            game.render_board(),
+            "--------------------
+CONNECT 4 (Move 3, opening, 7% full)
+P1 longest: 2 | P2 longest: 1
+--------------------
+1  2  3  4  5  6  7
+⚫ ⚫ ⚫ 🟡 ⚫ ⚫ ⚫
+⚫ ⚫ 🔴 🔴 ⚫ ⚫ ⚫
+--------------------
+"
+        );
+    }
+
+    #[test]
+    fn grow_up_shows_every_row_of_a_full_board() {
+        plain();
+        let mut game = play(&DRAWN_GAME);
+        game.display.grow_up = true;
+        let rendered = game.render_board();
+
+        let rows = rendered
+            .lines()
+            .filter(|line| line.contains('🔴') || line.contains('🟡'));
+        assert_eq!(rows.count(), BOARD_HEIGHT);
+        assert!(rendered.contains("P1 longest: "));
+    }
+
+    #[test]
+    fn threat_counts_follow_every_move_of_random_games() {
+        let builders = [
+            GameConfigBuilder::new(),
+            GameConfigBuilder::new().wrap(true),
+            GameConfigBuilder::new().tilt(Some(Tilt::Left)),
+            GameConfigBuilder::new().chaos(true),
+        ];
+
+        for (variant, builder) in builders.into_iter().enumerate() {
+            let start = builder
+                .build()
+                .unwrap_or_else(|_| panic!("variant {variant}"));
+
+            for seed in 0..20 {
+                let mut rng = Rng::from_seed(seed);
+                let mut game = start.clone();
+
+                while !game.is_finished {
+                    let columns = game.available_columns();
+                    game.play_move(columns[rng.below(columns.len())]).unwrap();
+
+                    assert_eq!(
+                        game.threats,
+                        game.count_threats(),
+                        "variant {variant}, seed {seed}"
+                    );
+                    for player in [Player::One, Player::Two] {
+                        let squares = game.threat_squares(player);
+                        let odd = squares
+                            .iter()
+                            .filter(|&&(row, _)| (BOARD_HEIGHT - row) % 2 == 1)
+                            .count();
+                        assert_eq!(game.threat_parity(player), (odd, squares.len() - odd));
+                    }
+                }
+
+                while game.undo().is_ok() {
+                    assert_eq!(
+                        game.threats,
+                        game.count_threats(),
+                        "variant {variant}, seed {seed}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn display_width_counts_discs_double_and_skips_escapes() {
+        assert_eq!(display_width("7"), 1);
+        assert_eq!(display_width("⚫"), 2);
+        assert_eq!(display_width("\x1b[31m🔴\x1b[0m"), 2);
+        assert_eq!(display_width("10"), 2);
+    }
+
+    #[test]
+    fn render_row_pads_cells_of_every_width() {
+        assert_eq!(render_row(&["1", "🔴", "v", "⚫", "7"]), "1  🔴 v  ⚫ 7");
+    }
+
+    #[test]
+    fn board_columns_line_up_with_the_numbers() {
+        plain();
+
+        assert_eq!(
+            play(&[4, 4, 3]).render_board(),
+            "--------------------
+CONNECT 4 (Move 3, opening, 7% full)
+P1 longest: 2 | P2 longest: 1
+--------------------
+1  2  3  4  5  6  7
+⚫ ⚫ ⚫ ⚫ ⚫ ⚫ ⚫
+⚫ ⚫ ⚫ ⚫ ⚫ ⚫ ⚫
+⚫ ⚫ ⚫ ⚫ ⚫ ⚫ ⚫
+⚫ ⚫ ⚫ ⚫ ⚫ ⚫ ⚫
+⚫ ⚫ ⚫ 🟡 ⚫ ⚫ ⚫
+⚫ ⚫ 🔴 🔴 ⚫ ⚫ ⚫
+--------------------
+"
+        );
+    }
+
+    fn hash_of(game: &Game) -> u64 {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        game.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn transposed_move_orders_compare_and_hash_equal() {
+        let one = play(&[4, 3, 5, 3]);
+        let two = play(&[5, 3, 4, 3]);
+
+        assert!(one == two);
+        assert_eq!(hash_of(&one), hash_of(&two));
+    }
+
+    #[test]
+    fn different_positions_are_not_equal() {
+        assert!(play(&[4, 3]) != play(&[3, 4]));
+        assert!(play(&[4]) != Game::default());
+        // Same discs, but a resignation finished one of them.
+        let mut resigned = play(&[4, 3]);
+        resigned.forfeit();
+        assert!(resigned != play(&[4, 3]));
+    }
+
+    #[test]
+    fn positions_work_as_map_keys() {
+        let mut seen = std::collections::HashMap::new();
+        seen.insert(play(&[4, 3, 5, 3]), "first");
+
+        assert_eq!(seen.get(&play(&[5, 3, 4, 3])), Some(&"first"));
+        assert_eq!(seen.get(&play(&[4, 3, 5, 2])), None);
+    }
+
+    // Rows top first, as in a diagram.
+    fn board(text: &str) -> Board {
+        let mut board = [[0; BOARD_WIDTH]; BOARD_HEIGHT];
+
+        for (row, line) in text.lines().enumerate() {
+            for (col, cell) in line.trim().chars().enumerate() {
+                board[row][col] = match cell {
+                    'X' => Player::One as u8,
+                    'O' => Player::Two as u8,
+                    _ => 0,
+                };
+            }
+        }
+
+        board
+    }
+
+    #[test]
+    fn from_parts_rebuilds_a_played_position() {
+        let played = play(&[4, 4, 3, 3, 2]);
+        let rebuilt = Game::from_parts(played.board, Player::Two, 5).unwrap();
+
+        assert!(rebuilt == played);
+        assert_eq!(rebuilt.winner, Player::None);
+        assert!(!rebuilt.is_finished);
+    }
+
+    #[test]
+    fn from_parts_derives_the_winner_from_the_board() {
+        let won = play(&[4, 4, 3, 3, 2, 2, 1]).board;
+        let rebuilt = Game::from_parts(won, Player::Two, 7).unwrap();
+
+        assert_eq!(rebuilt.winner, Player::One);
+        assert!(rebuilt.is_finished);
+    }
+
+    #[test]
+    fn from_parts_rejects_a_floating_disc() {
+        let board = board(
+            ".......
+             .......
+             .......
+             ...X...
+             .......
+             ...O...",
+        );
+
+        assert!(matches!(
+            Game::from_parts(board, Player::One, 2),
+            Err(ValidationError::FloatingDisc)
+        ));
+    }
+
+    #[test]
+    fn from_parts_rejects_the_wrong_side_to_move() {
+        let board = board(
+            ".......
+             .......
+             .......
+             .......
+             ...O...
+             ...X...",
+        );
+
+        assert!(matches!(
+            Game::from_parts(board, Player::Two, 2),
+            Err(ValidationError::WrongTurn)
+        ));
+        assert!(matches!(
+            Game::from_parts(board, Player::One, 3),
+            Err(ValidationError::WrongMoveCount)
+        ));
+    }
+
+    #[test]
+    fn from_parts_rejects_a_four_for_the_side_to_move() {
+        let board = board(
+            ".......
+             .......
+             .......
+             .......
+             OOO....
+             XXXXO..",
+        );
+
+        assert!(matches!(
+            Game::from_parts(board, Player::One, 8),
+            Err(ValidationError::ImpossibleWinner)
+        ));
+    }
+
+    #[test]
+    fn invalid_column_states_the_real_range() {
+        assert_eq!(
+            MoveError::InvalidColumn { max: 9 }.to_string(),
+            "column must be between 1 and 9"
+        );
+        assert_eq!(
+            Column::from_user(8).unwrap_err().to_string(),
+            "column must be between 1 and 7"
+        );
+        assert_eq!(
+            Game::default()
+                .play_move(Column(7))
+                .unwrap_err()
+                .to_string(),
+            "column must be between 1 and 7"
+        );
+    }
+
+    // A legal game of random moves, stopped at a random length or its end.
+    fn random_game(rng: &mut Rng) -> Game {
+        let mut game = Game::default();
+        let plies = rng.below(BOARD_WIDTH * BOARD_HEIGHT + 1);
+
+        while game.history.len() < plies && !game.is_finished {
+            let columns = game.available_columns();
+            game.play_move(columns[rng.below(columns.len())]).unwrap();
+        }
+
+        game
+    }
+
+    #[test]
+    fn every_format_round_trips_random_games() {
+        let mut rng = Rng::from_seed(184);
+
+        for round in 0..200 {
+            let game = random_game(&mut rng);
+
+            let loaded = Game::load_binary(&mut &binary_save(&game)[..]).unwrap();
+            assert!(loaded == game, "binary, round {round}");
+            assert_eq!(loaded.history, game.history, "binary, round {round}");
+
+            let sgf = Game::from_sgf(&game.to_sgf()).unwrap();
+            assert!(sgf == game, "SGF, round {round}");
+            assert_eq!(sgf.history, game.history, "SGF, round {round}");
+
+            let mut replayed = Game::default();
+            for column in Game::decode_moves(&game.encode_moves()).unwrap() {
+                replayed.play_move(column).unwrap();
+            }
+            assert!(replayed == game, "move log, round {round}");
+
+            // A diagram is only the board, so the side to move comes back
+            // from the disc counts and a won game's winner stays on turn.
+            let from_diagram = Game::from_diagram(&game.to_diagram()).unwrap();
+            let fingerprint = game.board_fingerprint();
+            let (rows, to_move) = fingerprint.split_once(':').unwrap();
+            let from_fingerprint = Game::from_diagram(&rows.replace('/', "\n")).unwrap();
+
+            for (format, copy) in [
+                ("diagram", &from_diagram),
+                ("fingerprint", &from_fingerprint),
+            ] {
+                assert_eq!(copy.board, game.board, "{format}, round {round}");
+                assert_eq!(copy.winner, game.winner, "{format}, round {round}");
+                assert_eq!(
+                    copy.is_finished, game.is_finished,
+                    "{format}, round {round}"
+                );
+                if !game.is_finished {
+                    assert!(copy == &game, "{format}, round {round}");
+                }
+            }
+            assert_eq!(from_fingerprint.board_fingerprint(), fingerprint);
+            assert!(["X", "O", "-"].contains(&to_move));
+        }
+    }
+
+    #[test]
+    fn column_reads_empty_partial_and_full_columns() {
+        let game = play(&[4, 4, 4, 3, 3, 3, 3, 3, 3]);
+        let view = game.board_view();
+
+        assert_eq!(view.column(0), vec![Player::None; BOARD_HEIGHT]);
+        assert_eq!(view.column_height(0), 0);
+
+        assert_eq!(
+            view.column(3),
+            [
+                Player::One,
+                Player::Two,
+                Player::One,
+                Player::None,
+                Player::None,
+                Player::None
+            ]
+        );
+        assert_eq!(view.column_height(3), 3);
+
+        assert_eq!(
+            view.column(2),
+            [
+                Player::Two,
+                Player::One,
+                Player::Two,
+                Player::One,
+                Player::Two,
+                Player::One
+            ]
+        );
+        assert_eq!(view.column_height(2), BOARD_HEIGHT);
+        assert!(!game.available_columns().contains(&Column(2)));
+    }
+
+    #[test]
+    fn display_options_survive_building_and_undo() {
+        let display = DisplayOptions {
+            grow_up: true,
+            show_hud: true,
+            frame_style: FrameStyle::Box,
+            ..DisplayOptions::default()
+        };
+        let mut game = GameConfigBuilder::new()
+            .display(display)
+            .moves(vec![Column(3), Column(3)])
+            .build()
+            .unwrap_or_else(|_| panic!("the moves are legal"));
+
+        assert_eq!(game.display, display);
+        game.undo().unwrap();
+        assert_eq!(game.display, display);
+    }
+
+    #[test]
+    fn protocol_answers_a_command_script() {
+        let options = Options::default();
+        let script = "MOVE 4\nmove 4\nUNDO\nSTATE\nMOVE 9\nMOVE\nPASS\n\
+                      MOVE 4\nMOVE 1\nMOVE 4\nMOVE 1\nMOVE 4\nMOVE 1\nMOVE 4\n\
+                      MOVE 2\nRESET\nUNDO\nQUIT\nMOVE 4\n";
+        let mut out = Vec::new();
+
+        run_protocol(
+            &options,
+            options.new_game(),
+            io::Cursor::new(script),
+            &mut out,
+        )
+        .unwrap();
+
+        let expected = [
+            "OK",
+            "OK",
+            "OK",
+            "STATE ......./......./......./......./......./...X...:O",
+            "ILLEGAL column must be between 1 and 7",
+            "ERROR MOVE needs a column",
+            "ERROR unknown command 'PASS'",
+            "OK",
+            "OK",
+            "OK",
+            "OK",
+            "OK",
+            "OK",
+            // Player 2 stacks four on top of Player 1's first disc.
+            "WIN 2",
+            "ILLEGAL game is already finished",
+            "OK",
+            "ILLEGAL no move to undo",
+            "OK",
+        ];
+
+        // Nothing after QUIT is read.
+        assert_eq!(
+            String::from_utf8(out).unwrap().lines().collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn training_finds_a_position_for_each_pattern() {
+        let options = Options::default();
+        let mut rng = Rng::from_seed(211);
+
+        for pattern in [TrainPattern::Win, TrainPattern::Block] {
+            let (game, answer) = training_position(&options, pattern, &mut rng).unwrap();
+
+            assert!(!game.is_finished);
+            assert_eq!(pattern.answer(&game), Some(answer));
+        }
+    }
+
+    #[test]
+    fn training_gives_up_when_the_rules_never_set_the_pattern() {
+        // Games end before the first position training looks at.
+        let options = Options {
+            turn_limit: Some(TRAIN_MIN_PLIES - 1),
+            ..Options::default()
+        };
+        let mut rng = Rng::from_seed(211);
+
+        assert!(matches!(
+            training_position(&options, TrainPattern::Win, &mut rng),
+            Err(GameError::NoTrainingPosition(TRAIN_MAX_PLAYOUTS))
+        ));
+    }
+
+    #[test]
+    fn eval_cache_stays_within_its_size_and_agrees_with_an_uncapped_one() {
+        let mut tiny = EvalCache::new(3);
+        let mut roomy = EvalCache::new(1 << 12);
+        let mut rng = Rng::from_seed(218);
+
+        for _ in 0..20 {
+            let game = random_game(&mut rng);
+            if game.is_finished {
+                continue;
+            }
+            // Best replies by the cached evaluation, seen from the mover.
+            let best = |cache: &mut EvalCache| {
+                let sign = if game.current_player == Player::One {
+                    1
+                } else {
+                    -1
+                };
+                game.available_columns().into_iter().max_by_key(|&column| {
+                    let mut after = game.clone();
+                    after.play_move(column).unwrap();
+                    sign * cache.evaluate(&after)
+                })
+            };
+
+            assert_eq!(tiny.evaluate(&game), game.evaluate());
+            assert_eq!(best(&mut tiny), best(&mut roomy));
+            assert_eq!(tiny.slots.len(), 3);
+        }
+
+        assert!(roomy.slots.iter().flatten().count() > 3);
+    }
+
+    // Likes a move more the further left it was played.
+    struct LeftmostEvaluator;
+
+    impl Evaluator for LeftmostEvaluator {
+        fn evaluate(&self, game: &Game, _player: Player) -> i32 {
+            game.history.last().map_or(0, |column| -(column.0 as i32))
+        }
+    }
+
+    #[test]
+    fn principal_variation_follows_the_evaluator() {
+        let game = Game::default();
+
+        assert_eq!(game.principal_variation(1, &ThreatEvaluator), [Column(3)]);
+        assert_eq!(game.principal_variation(1, &LeftmostEvaluator), [Column(0)]);
+        assert_eq!(
+            game.principal_variation(3, &LeftmostEvaluator),
+            [Column(0), Column(0), Column(0)]
+        );
+    }
+
+    #[test]
+    fn a_four_on_the_last_allowed_move_is_an_outright_win() {
+        // Komi would hand Player 2 a scored result.
+        let game = GameConfigBuilder::new()
+            .turn_limit(Some(7))
+            .komi(Some(10.0))
+            .moves(
+                [1, 2, 1, 2, 1, 2, 1]
+                    .map(|number| Column(number - 1))
+                    .to_vec(),
+            )
+            .build()
+            .unwrap_or_else(|_| panic!("the moves are legal"));
+
+        assert!(game.is_finished);
+        assert_eq!(game.winner, Player::One);
+        assert!(matches!(game.outcome(), GameOutcome::Win(Player::One)));
+    }
+
+    #[test]
+    fn exact_length_wins_past_a_longer_line_in_another_direction() {
+        let mut game = diagram(
+            ".......
+             .......
+             XX.XX..
+             OOXOO..
+             OXXOXO.
+             OXXOOXO",
+        );
+        game.exact_length = true;
+
+        // Column 3 makes five across and exactly four down.
+        game.play_move(Column(2)).unwrap();
+
+        assert_eq!(game.longest_run(Player::One), 5);
+        assert_eq!(game.winner, Player::One);
+        assert!(game.is_finished);
+    }
+
+    #[test]
+    fn self_check_passes_on_the_stock_engine() {
+        let report = self_check();
+
+        assert!(!report.is_empty());
+        for (name, result) in report {
+            assert!(result.is_ok(), "{}: {:?}", name, result);
+        }
+    }
+
+    // Fails each read with the errors given, in order, then reads `rest`.
+    struct FlakyReader {
+        errors: Vec<io::ErrorKind>,
+        rest: io::Cursor<&'static str>,
+    }
+
+    impl Read for FlakyReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.errors.pop() {
+                Some(kind) => Err(io::Error::new(kind, "flaky")),
+                None => self.rest.read(buf),
+            }
+        }
+    }
+
+    #[test]
+    fn read_input_reports_a_failing_reader_as_an_error() {
+        let mut input = io::BufReader::new(FlakyReader {
+            errors: vec![io::ErrorKind::BrokenPipe],
+            rest: io::Cursor::new("4\n"),
+        });
+
+        assert!(matches!(read_input(&mut input), Err(GameError::Io(_))));
+    }
+
+    #[test]
+    fn read_input_retries_an_interrupted_read_and_reports_a_closed_one() {
+        let mut input = io::BufReader::new(FlakyReader {
+            errors: vec![io::ErrorKind::Interrupted],
+            rest: io::Cursor::new("4\n"),
+        });
+
+        assert_eq!(read_input(&mut input).unwrap(), "4\n");
+        assert!(matches!(
+            read_input(&mut input),
+            Err(GameError::InputClosed)
+        ));
+    }
+
+    #[test]
+    fn the_last_open_column_is_forced_and_can_be_undone() {
+        let mut game = play(&DRAWN_GAME[..DRAWN_GAME.len() - 1]);
+        assert_eq!(game.forced_column(), Some(Column(6)));
+        assert_eq!(play(&[4]).forced_column(), None);
+
+        game.play_move(game.forced_column().unwrap()).unwrap();
+        assert!(game.is_finished);
+        assert_eq!(game.winner, Player::None);
+        assert_eq!(game.forced_column(), None);
+
+        game.undo().unwrap();
+        assert!(!game.is_finished);
+        assert_eq!(game.forced_column(), Some(Column(6)));
+    }
+
+    // A board one disc short of full, with no four, where Player 1's longest
+    // run is three and Player 2's two. Player 2 fills the last cell.
+    const RUNS_THREE_AGAINST_TWO: &str = ".OXOXOX
+                                          XOOXOOX
+                                          OXXXOXO
+                                          XOOXXXO
+                                          OOXOXOX
+                                          XXXOXOO";
+
+    fn fill_last_cell(tiebreak: Tiebreak) -> Game {
+        let mut game = GameConfigBuilder::new()
+            .start_board(board(RUNS_THREE_AGAINST_TWO))
+            .tiebreak(tiebreak)
+            .build()
+            .unwrap_or_else(|_| panic!("the board is a legal start"));
+        game.play_move(Column(0)).unwrap();
+        game
+    }
+
+    #[test]
+    fn longest_run_rules_award_a_full_board_to_the_longer_run() {
+        let game = fill_last_cell(Tiebreak::LongestRun);
+
+        assert_eq!(game.longest_run(Player::One), 3);
+        assert_eq!(game.longest_run(Player::Two), 2);
+        assert!(game.is_finished);
+        assert_eq!(game.winner, Player::One);
+        assert_eq!(fill_last_cell(Tiebreak::Draw).winner, Player::None);
+    }
+
+    #[test]
+    fn columns_convert_at_the_edges_of_the_board() {
+        assert!(matches!(
+            Column::from_user(0),
+            Err(MoveError::InvalidColumn { max: 7 })
+        ));
+        assert_eq!(Column::from_user(1).unwrap(), Column(0));
+        assert_eq!(Column::from_user(7).unwrap(), Column(6));
+        assert!(matches!(
+            Column::from_user(8),
+            Err(MoveError::InvalidColumn { max: 7 })
+        ));
+
+        for number in 1..=BOARD_WIDTH {
+            assert_eq!(Column::from_user(number).unwrap().to_user(), number);
+        }
+    }
+
+    #[test]
+    fn the_turn_alternates_across_a_full_game() {
+        let mut game = Game::default();
+
+        for (ply, &number) in DRAWN_GAME.iter().enumerate() {
+            let expected = if ply % 2 == 0 {
+                Player::One
+            } else {
+                Player::Two
+            };
+            assert_eq!(game.side_to_move(), expected, "ply {ply}");
+            assert_ne!(game.next_player(), expected, "ply {ply}");
+
+            game.play_move(Column(number - 1)).unwrap();
+        }
+
+        assert_eq!(game.side_to_move(), Player::None);
+    }
+
+    #[test]
+    fn standard_rules_accept_open_columns_and_nothing_else() {
+        let rules = StandardRules;
+        let game = play(&[1, 1, 1, 1, 1, 1]);
+
+        assert!(rules.validate(&game, Column(3)).is_ok());
+        assert!(matches!(
+            rules.validate(&game, Column(0)),
+            Err(MoveError::ColumnFull)
+        ));
+        assert!(matches!(
+            rules.validate(&game, Column(7)),
+            Err(MoveError::InvalidColumn { max: 7 })
+        ));
+        assert!(matches!(
+            rules.validate(&play(&[1, 2, 1, 2, 1, 2, 1]), Column(3)),
+            Err(MoveError::GameFinished)
+        ));
+
+        // Nothing happens after a standard move beyond the drop itself.
+        let mut after = play(&[4, 3]);
+        let before = after.clone();
+        StandardRules.post_apply(&mut after);
+        assert!(after == before);
+        assert_eq!(rules.name(), "standard");
+    }
+
+    #[test]
+    fn detect_patterns_finds_a_seven_either_way_round() {
+        let seven = diagram(
+            ".......
+             .......
+             .......
+             ..XXX..
+             ..OXO..
+             ..OOX..",
+        );
+        assert!(seven
+            .detect_patterns(Player::One)
+            .contains(&Pattern::Seven { row: 3, col: 2 }));
+
+        // Mirrored, and against the left edge.
+        let mirrored = diagram(
+            ".......
+             .......
+             .......
+             XXX....
+             OXO....
+             XOO....",
+        );
+        assert!(mirrored
+            .detect_patterns(Player::One)
+            .contains(&Pattern::Seven { row: 3, col: 2 }));
+        assert!(!mirrored
+            .detect_patterns(Player::Two)
+            .iter()
+            .any(|pattern| matches!(pattern, Pattern::Seven { .. })));
+    }
+
+    #[test]
+    fn detect_patterns_finds_a_baseline_double_threat() {
+        let game = diagram(
+            ".......
+             .......
+             .......
+             .......
+             .OOO...
+             .XXX...",
+        );
+
+        assert!(game
+            .detect_patterns(Player::One)
+            .contains(&Pattern::BaselineDoubleThreat { col: 0 }));
+    }
+
+    #[test]
+    fn detect_patterns_finds_a_floating_threat_on_the_right_parity() {
+        let game = diagram(
+            ".......
+             .......
+             .......
+             XXX....
+             OOX....
+             XOO....",
+        );
+
+        assert!(game
+            .detect_patterns(Player::One)
+            .contains(&Pattern::OddEvenThreat { row: 3, col: 3 }));
+        assert!(Game::default().detect_patterns(Player::One).is_empty());
+        assert!(game.detect_patterns(Player::None).is_empty());
+    }
+
+    #[test]
+    fn threat_parity_splits_threats_by_row_from_the_bottom() {
+        let game = diagram(
+            ".......
+             .......
+             .......
+             XXX....
+             OOO....
+             XOX....",
+        );
+
+        assert_eq!(game.threat_squares(Player::One), [(3, 3)]);
+        assert_eq!(game.threat_squares(Player::Two), [(4, 3)]);
+        assert_eq!(game.threat_parity(Player::One), (1, 0));
+        assert_eq!(game.threat_parity(Player::Two), (0, 1));
+        assert_eq!(game.threat_parity(Player::None), (0, 0));
+
+        // An odd threat is worth the most to Player 1, and an even one to
+        // Player 2, so these two cancel out.
+        assert_eq!(game.evaluate(), 0);
+    }
+
+    #[test]
+    fn move_codes_round_trip_at_every_length() {
+        for length in [0, 1, 2, 3, 8, 13, DRAWN_GAME.len()] {
+            let game = play(&DRAWN_GAME[..length]);
+            let bytes = game.encode_moves();
+
+            assert_eq!(bytes.len(), 2 + (length * MOVE_BITS).div_ceil(8));
+            assert_eq!(
+                Game::decode_moves(&bytes).unwrap(),
+                game.history,
+                "length {length}"
+            );
+        }
+    }
+
+    #[test]
+    fn move_codes_reject_a_column_off_the_board_and_bad_lengths() {
+        // One move coded as 7, where the last column is 6.
+        assert!(matches!(
+            Game::decode_moves(&[1, 0, 0b111]),
+            Err(DecodeError::InvalidColumn(0, 7))
+        ));
+
+        let bytes = play(&[4, 4, 3]).encode_moves();
+        assert!(matches!(
+            Game::decode_moves(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::Truncated)
+        ));
+        assert!(matches!(
+            Game::decode_moves(&[bytes.clone(), vec![0]].concat()),
+            Err(DecodeError::TrailingBytes)
+        ));
+        assert!(matches!(
+            Game::decode_moves(&[1]),
+            Err(DecodeError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn gauge_scales_with_the_value_and_clamps_at_the_range() {
+        plain();
+
+        assert_eq!(render_gauge(0, 10, 8), "[####----]");
+        assert_eq!(render_gauge(5, 10, 8), "[######--]");
+        assert_eq!(render_gauge(-5, 10, 8), "[##------]");
+        assert_eq!(render_gauge(10, 10, 8), "[########]");
+        assert_eq!(render_gauge(WIN_SCORE, 10, 8), "[########]");
+        assert_eq!(render_gauge(-WIN_SCORE, 10, 8), "[--------]");
+    }
+
+    fn build_with(config: GameConfigBuilder, moves: &[usize]) -> Game {
+        config
+            .moves(moves.iter().map(|&number| Column(number - 1)).collect())
+            .build()
+            .unwrap_or_else(|_| panic!("the moves are legal"))
+    }
+
+    // Player 1 finishes a diagonal four rising from column 1 to column 4.
+    const DIAGONAL_FOUR: [usize; 11] = [1, 2, 2, 3, 3, 4, 3, 4, 4, 1, 4];
+
+    #[test]
+    fn no_diagonals_ignores_a_diagonal_four_but_not_a_vertical_one() {
+        let orthogonal = || {
+            GameConfigBuilder::new().win_directions(
+                WinDirections::ALL
+                    .without(WinDirections::DIAGONAL_DOWN)
+                    .without(WinDirections::DIAGONAL_UP),
+            )
+        };
+
+        assert_eq!(play(&DIAGONAL_FOUR).winner, Player::One);
+
+        let game = build_with(orthogonal(), &DIAGONAL_FOUR);
+        assert_eq!(game.winner, Player::None);
+        assert!(!game.is_finished);
+
+        let game = build_with(orthogonal(), &[1, 2, 1, 2, 1, 2, 1]);
+        assert_eq!(game.winner, Player::One);
+    }
+
+    // An empty directory of its own for a test that writes files.
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("connect-dots-game-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn frame_export_writes_one_frame_per_position() {
+        let dir = scratch_dir("frames");
+        let game = play(&[4, 4, 3, 5, 2]);
+
+        game.render_frames_to_dir(&dir).unwrap();
+
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), game.history.len() + 1);
+        assert_eq!(names.first().map(String::as_str), Some("frame_000.ppm"));
+        assert_eq!(names.last().map(String::as_str), Some("frame_005.ppm"));
+
+        let last = std::fs::read(dir.join("frame_005.ppm")).unwrap();
+        assert_eq!(last, game.render_ppm());
+        assert!(last.starts_with(b"P6\n280 240\n255\n"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn safe_play_refuses_a_losing_move_while_another_is_safe() {
+        // Player 2's three on the second row is open at both ends.
+        let game = diagram(
+            ".......
+             .......
+             .......
+             ..X....
+             .OOO...
+             .XOX.X.",
+        );
+
+        assert!(game.is_losing_move(Column(0)));
+        assert!(game.refuses_move(Column(0)));
+        assert!(game.refuses_move(Column(4)));
+        assert!(!game.refuses_move(Column(1)));
+    }
+
+    // The only open column leaves Player 2 the top cell for a four.
+    const ONLY_MOVE_LOSES: &str = "XOXOOO.
+                                   OXXOXX.
+                                   XXOXOOX
+                                   OXXXOXO
+                                   OOOXXXO
+                                   OXXOOXO";
+
+    #[test]
+    fn safe_play_allows_a_losing_move_when_every_move_loses() {
+        let game = diagram(ONLY_MOVE_LOSES);
+
+        assert_eq!(game.available_columns(), [Column(6)]);
+        assert!(game.is_losing_move(Column(6)));
+        assert!(!game.refuses_move(Column(6)));
+    }
+
+    #[test]
+    fn phase_changes_at_each_third_of_the_board() {
+        let phase = |plies: usize| play(&DRAWN_GAME[..plies]).phase();
+
+        assert_eq!(phase(0), Phase::Opening);
+        assert_eq!(phase(13), Phase::Opening);
+        assert_eq!(phase(14), Phase::Midgame);
+        assert_eq!(phase(27), Phase::Midgame);
+        assert_eq!(phase(28), Phase::Endgame);
+        assert_eq!(phase(42), Phase::Endgame);
+    }
+
+    #[test]
+    fn annotated_game_has_one_line_per_move() {
+        let report = play(&[4, 1, 4, 1, 4, 7, 4]).annotate_game();
+
+        assert_eq!(
+            report.lines().collect::<Vec<_>>(),
+            [
+                "1. P1 column 4: good",
+                "2. P2 column 1: good",
+                "3. P1 column 4: good",
+                "4. P2 column 1: good",
+                "5. P1 column 4: good",
+                "6. P2 column 7: blunder, allows a win in column 4",
+                "7. P1 column 4: best, wins the game",
+            ]
+        );
+    }
+
+    #[test]
+    fn drill_replays_the_logged_replies_while_they_are_followed() {
+        let logged = play(&[4, 4, 3, 5, 2, 1, 6, 7]);
+        let mut drill = DrillSource {
+            moves: logged.history.clone(),
+            diverged: false,
+        };
+        let mut game = Game::default();
+
+        for &column in &logged.history {
+            if game.current_player == Player::Two {
+                let reply = drill.next_move(&game).unwrap();
+                assert!(matches!(reply, Command::Drop(reply) if reply == column));
+            }
+            game.play_move(column).unwrap();
+        }
+
+        assert!(!drill.diverged);
+        assert_eq!(game.history, logged.history);
+    }
+
+    #[test]
+    fn zugzwang_when_every_move_makes_things_worse() {
+        assert!(diagram(ONLY_MOVE_LOSES).is_zugzwang());
+        assert!(!Game::default().is_zugzwang());
+        assert!(!play(&[4, 4, 4]).is_zugzwang());
+    }
+
+    #[test]
+    fn too_many_bad_inputs_forfeit_the_game() {
+        plain();
+        let options = Options {
+            max_retries: Some(3),
+            retry_limit: RetryLimit::Forfeit,
+            ..Options::default()
+        };
+        let mut game = play(&[4]);
+        let mut retries = 0;
+
+        for _ in 0..3 {
+            assert!(!retry_exhausted(&mut game, &options, &mut retries));
+            assert!(!game.is_finished);
+        }
+
+        assert!(!retry_exhausted(&mut game, &options, &mut retries));
+        assert!(game.is_finished);
+        assert_eq!(game.winner, Player::One);
+        assert_eq!(retries, 0);
+    }
+
+    #[test]
+    fn too_many_bad_inputs_can_exit_instead() {
+        let options = Options {
+            max_retries: Some(2),
+            retry_limit: RetryLimit::Exit,
+            ..Options::default()
+        };
+        let mut game = Game::default();
+        let mut retries = 0;
+
+        assert!(!retry_exhausted(&mut game, &options, &mut retries));
+        assert!(!retry_exhausted(&mut game, &options, &mut retries));
+        assert!(retry_exhausted(&mut game, &options, &mut retries));
+        assert!(!game.is_finished);
+    }
+
+    #[test]
+    fn bad_inputs_are_unlimited_by_default() {
+        let options = Options::default();
+        let mut game = Game::default();
+        let mut retries = 0;
+
+        for _ in 0..100 {
+            assert!(!retry_exhausted(&mut game, &options, &mut retries));
+        }
+        assert!(!game.is_finished);
+    }
+
+    fn config_error(config: GameConfigBuilder) -> ConfigError {
+        match config.build() {
+            Ok(_) => panic!("the config should be rejected"),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn game_config_builds_a_valid_game() {
+        let game = GameConfigBuilder::new()
+            .win_lengths(5, 3)
+            .turn_limit(Some(20))
+            .mercy(Some(4))
+            .moves(vec![Column(3), Column(2)])
+            .build()
+            .unwrap_or_else(|err| panic!("{}", err));
+
+        assert_eq!(game.win_length_orthogonal, 5);
+        assert_eq!(game.win_length_diagonal, 3);
+        assert_eq!(game.turn_limit, Some(20));
+        assert_eq!(game.history, [Column(3), Column(2)]);
+    }
+
+    #[test]
+    fn game_config_rejects_each_invalid_setting() {
+        let builder = GameConfigBuilder::new;
+        let columns = |numbers: &[usize]| numbers.iter().map(|&n| Column(n - 1)).collect();
+
+        assert!(matches!(
+            config_error(builder().diagram("XO")),
+            ConfigError::InvalidPosition(DiagramError::WrongDimensions)
+        ));
+        assert!(matches!(
+            config_error(builder().win_directions(WinDirections(0))),
+            ConfigError::NoWinDirections
+        ));
+        assert!(matches!(
+            config_error(builder().win_lengths(1, 4)),
+            ConfigError::InvalidWinLength(1)
+        ));
+        assert!(matches!(
+            config_error(builder().win_lengths(4, 7)),
+            ConfigError::InvalidWinLength(7)
+        ));
+        assert!(matches!(
+            config_error(builder().moves(columns(&[1, 1, 1, 1, 1, 1, 1]))),
+            ConfigError::InvalidMove(6, MoveError::ColumnFull)
+        ));
+        assert!(matches!(
+            config_error(
+                builder()
+                    .start_board(board(
+                        ".......\n.......\n.......\n.......\n.......\n...X..."
+                    ))
+                    .handicap(columns(&[4]))
+            ),
+            ConfigError::HandicapNeedsEmptyBoard
+        ));
+        assert!(matches!(
+            config_error(builder().handicap(columns(&[2, 2, 2, 2, 2, 2, 2]))),
+            ConfigError::InvalidHandicap(6, MoveError::ColumnFull)
+        ));
+        assert!(matches!(
+            config_error(builder().handicap(columns(&[1, 2, 3, 4]))),
+            ConfigError::WinningHandicap
+        ));
+        assert!(matches!(
+            config_error(builder().center_gravity(true).tilt(Some(Tilt::Left))),
+            ConfigError::CenterGravityWithTilt
+        ));
+        assert!(matches!(
+            config_error(builder().mercy(Some(3))),
+            ConfigError::MercyWithoutTurnLimit
+        ));
+        assert!(matches!(
+            config_error(builder().mercy(Some(3)).turn_limit(Some(20)).chaos(true)),
+            ConfigError::MercyWithMovingDiscs
+        ));
+    }
+
+    #[test]
+    fn threat_timeline_records_created_blocked_and_converted_threats() {
+        let event = |move_number, player, change| ThreatEvent {
+            move_number,
+            player,
+            row: 5,
+            col: 3,
+            change,
+        };
+
+        assert_eq!(
+            play(&[1, 1, 2, 2, 3, 4]).threat_timeline(),
+            [
+                event(5, Player::One, ThreatChange::Created),
+                event(6, Player::One, ThreatChange::Blocked),
+            ]
+        );
+        assert_eq!(
+            play(&[1, 1, 2, 2, 3, 7, 4]).threat_timeline(),
+            [
+                event(5, Player::One, ThreatChange::Created),
+                ThreatEvent {
+                    col: 4,
+                    ..event(7, Player::One, ThreatChange::Created)
+                },
+                event(7, Player::One, ThreatChange::Converted),
+            ]
+        );
+        assert!(play(&[1, 2]).threat_timeline().is_empty());
+    }
+
+    #[test]
+    fn board_view_reads_cells_top_row_first() {
+        let game = play(&[1, 1, 7]);
+        let view = game.board_view();
+
+        assert_eq!((view.width(), view.height()), (7, 6));
+        assert_eq!(view.get(5, 0), Some(Player::One));
+        assert_eq!(view.get(4, 0), Some(Player::Two));
+        assert_eq!(view.get(0, 0), Some(Player::None));
+        assert_eq!(view.get(6, 0), None);
+        assert_eq!(view.get(0, 7), None);
+        assert_eq!(view.column_height(0), 2);
+        assert_eq!(view.column(6)[0], Player::One);
+
+        let cells: Vec<_> = view.cells().collect();
+        assert_eq!(cells.len(), 42);
+        assert_eq!(cells[0], (0, 0, Player::None));
+        assert_eq!(cells[6], (0, 6, Player::None));
+        assert_eq!(cells[7], (1, 0, Player::None));
+        assert_eq!(cells[34], (4, 6, Player::None));
+        assert_eq!(cells[35], (5, 0, Player::One));
+        assert_eq!(cells[41], (5, 6, Player::One));
+    }
+
+    #[test]
+    fn strict_report_lists_missed_wins_and_blocks() {
+        assert_eq!(
+            play(&[4, 1, 4, 1, 4, 7, 2, 4]).strict_report(),
+            [
+                "move 6: failed to block col 4",
+                "move 7: missed win at col 4"
+            ]
+        );
+        assert!(play(&[4, 1, 4, 1, 4, 4]).strict_report().is_empty());
+    }
+
+    #[test]
+    fn turn_limit_scores_the_game_on_the_last_allowed_move() {
+        let limited = || GameConfigBuilder::new().turn_limit(Some(5));
+
+        let open = build_with(limited(), &[1, 1, 2, 2]);
+        assert!(!open.is_finished);
+        assert!(open.render_board().contains("1 move left"));
+
+        // Player 1's open three scores one threat square to none.
+        let scored = build_with(limited(), &[1, 1, 2, 2, 3]);
+        assert!(scored.is_finished);
+        assert_eq!(scored.winner, Player::One);
+
+        let level = build_with(limited(), &[1, 7, 1, 7, 4]);
+        assert!(level.is_finished);
+        assert!(matches!(level.outcome(), GameOutcome::Draw));
+    }
+
+    #[test]
+    fn komi_flips_a_scored_result_to_player_two() {
+        let scored = |komi| {
+            build_with(
+                GameConfigBuilder::new().turn_limit(Some(5)).komi(komi),
+                &[1, 1, 2, 2, 3],
+            )
+            .winner
+        };
+
+        // Player 1 scores one threat square to Player 2's none.
+        assert_eq!(scored(None), Player::One);
+        assert_eq!(scored(Some(0.5)), Player::One);
+        assert_eq!(scored(Some(1.0)), Player::Two);
+        assert_eq!(scored(Some(1.5)), Player::Two);
+    }
+
+    #[test]
+    fn legal_moves_with_outcomes_labels_wins_and_neutral_moves() {
+        let game = play(&[4, 1, 4, 1, 4, 7]);
+        let outcomes = game.legal_moves_with_outcomes();
+
+        assert_eq!(outcomes.len(), 7);
+        for (column, outcome) in outcomes {
+            if column == Column(3) {
+                assert_eq!(outcome, GameOutcome::Win(Player::One));
+            } else {
+                assert_eq!(outcome, GameOutcome::Ongoing);
+            }
+        }
+        assert_eq!(game.history.len(), 6);
+
+        let last = play(&DRAWN_GAME[..41]);
+        assert_eq!(
+            last.legal_moves_with_outcomes(),
+            [(Column(DRAWN_GAME[41] - 1), GameOutcome::Draw)]
+        );
+        assert!(play(&DRAWN_GAME).legal_moves_with_outcomes().is_empty());
+    }
+
+    #[test]
+    fn threat_squares_cover_edges_and_stacked_shared_squares() {
+        let edge = diagram(
+            ".......
+             .......
+             .......
+             X......
+             X......
+             XOOO...",
+        );
+        assert_eq!(edge.threat_squares(Player::One), [(2, 0)]);
+        assert_eq!(edge.threat_squares(Player::Two), [(5, 4)]);
+        assert!(edge.threat_squares(Player::None).is_empty());
+
+        // Both players threaten both squares of the gap in column 4.
+        let stacked = diagram(
+            ".......
+             .......
+             .......
+             .......
+             OOO.XXX
+             XXX.OOO",
+        );
+        assert_eq!(stacked.threat_squares(Player::One), [(4, 3), (5, 3)]);
+        assert_eq!(stacked.threat_squares(Player::Two), [(4, 3), (5, 3)]);
+    }
+
+    #[test]
+    fn clock_flag_falls_when_a_budget_runs_out() {
+        let mut clock = Clock::parse("0:10+2").unwrap_or_else(|err| panic!("{}", err));
+        assert_eq!(clock.remaining, [Duration::from_secs(10); 2]);
+        assert!(Clock::parse("10").is_err());
+        assert!(Clock::parse("1:x0").is_err());
+
+        assert!(clock.charge(Player::One, Duration::from_secs(4)));
+        assert_eq!(clock.remaining[0], Duration::from_secs(8));
+        assert_eq!(clock.remaining[1], Duration::from_secs(10));
+        assert_eq!(clock.render(), "P1 00:08 | P2 00:10");
+
+        // Using up the budget exactly is a loss, increment or not.
+        assert!(!clock.charge(Player::Two, Duration::from_secs(10)));
+        assert_eq!(clock.remaining[1], Duration::ZERO);
+        assert!(!clock.charge(Player::One, Duration::from_secs(60)));
+        assert_eq!(clock.remaining[0], Duration::ZERO);
+
+        let mut game = GameConfigBuilder::new()
+            .clock(Some(clock))
+            .build()
+            .unwrap_or_else(|err| panic!("{}", err));
+        game.forfeit();
+        assert!(matches!(game.outcome(), GameOutcome::Win(Player::Two)));
+    }
+
+    #[test]
+    fn chaos_colours_make_a_four_that_standard_rules_do_not() {
+        // Moves 3, 4, 7 and 8 drop the other colour, so Player 2's last
+        // move completes Player 1's bottom row.
+        let moves = [1, 7, 5, 2, 3, 7, 6, 4];
+        let chaos = || build_with(GameConfigBuilder::new().chaos(true), &moves);
+
+        let game = chaos();
+        assert!(matches!(game.outcome(), GameOutcome::Win(Player::One)));
+        assert_eq!(game.board[5], [1, 1, 1, 1, 2, 2, 2]);
+        assert!(chaos().board == game.board);
+
+        let standard = build_with(GameConfigBuilder::new(), &moves);
+        assert_eq!(standard.outcome(), GameOutcome::Ongoing);
+        assert_eq!(standard.board[5], [1, 2, 1, 2, 1, 1, 2]);
+    }
+
+    #[test]
+    fn redo_replays_undone_moves_until_a_new_move_is_played() {
+        let mut game = play(&[4, 3, 5]);
+        let played = game.clone();
+
+        game.undo().unwrap();
+        game.undo().unwrap();
+        assert!(game.board == play(&[4]).board);
+        assert_eq!(game.redo, [Column(4), Column(2)]);
+
+        game.redo().unwrap();
+        assert!(game.board == play(&[4, 3]).board);
+        assert_eq!(game.redo, [Column(4)]);
+        game.redo().unwrap();
+        assert!(game.board == played.board);
+        assert_eq!(game.history, played.history);
+        assert_eq!(game.current_player, played.current_player);
+        assert!(matches!(game.redo(), Err(MoveError::NothingToRedo)));
+
+        game.undo().unwrap();
+        game.undo().unwrap();
+        game.play_move(Column(0)).unwrap();
+        assert!(game.redo.is_empty());
+        assert!(game.board == play(&[4, 1]).board);
+        assert!(matches!(game.redo(), Err(MoveError::NothingToRedo)));
+    }
+
+    #[test]
+    fn missed_block_flags_only_an_ignored_single_threat() {
+        // Player 1 threatens column 4; Player 2 is to move.
+        let threatened = play(&[4, 1, 4, 1, 4]);
+        assert_eq!(threatened.missed_block(Column(6), &[]), Some(Column(3)));
+        assert_eq!(threatened.missed_block(Column(3), &[]), None);
+        assert_eq!(threatened.missed_block(Column(6), &[Column(3)]), None);
+
+        assert_eq!(play(&[4, 1]).missed_block(Column(6), &[]), None);
+    }
+
+    #[test]
+    fn lines_source_answers_with_the_recorded_line() {
+        let dir = scratch_dir("lines");
+        let path = dir.join("lines.txt");
+        std::fs::write(&path, "# centre openings\n4 4 3 5 2\n\n4 3 4 4\n").unwrap();
+        let lines = LineTrie::from_file(path.to_str().unwrap()).unwrap();
+
+        let mut source = LinesSource {
+            lines,
+            left_book: false,
+        };
+        let mut game = play(&[4]);
+        let reply = source.next_move(&game).unwrap();
+        assert!(matches!(reply, Command::Drop(Column(3))));
+
+        game.play_move(Column(3)).unwrap();
+        game.play_move(Column(2)).unwrap();
+        let reply = source.next_move(&game).unwrap();
+        assert!(matches!(reply, Command::Drop(Column(4))));
+        assert!(!source.left_book);
+
+        // A move off every line has nothing left to follow.
+        assert!(source.lines.follow(&play(&[4, 4, 6]).history).is_none());
+        assert!(source.lines.follow(&play(&[4, 3, 4]).history).is_some());
+
+        std::fs::write(&path, "4 4\n4 x\n").unwrap();
+        let err = LineTrie::from_file(path.to_str().unwrap()).err().unwrap();
+        assert!(err.starts_with("line 2: 'x'"), "{}", err);
+        std::fs::write(&path, "1 1 1 1 1 1 1\n").unwrap();
+        let err = LineTrie::from_file(path.to_str().unwrap()).err().unwrap();
+        assert!(err.starts_with("line 1, move 7"), "{}", err);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn fill_ratio_and_phase_follow_the_discs_on_the_board() {
+        let empty = Game::default();
+        assert_eq!((empty.filled(), empty.capacity()), (0, 42));
+        assert_eq!(empty.fill_ratio(), 0.0);
+        assert_eq!(empty.phase(), Phase::Opening);
+
+        let half = play(&DRAWN_GAME[..21]);
+        assert_eq!(half.filled(), 21);
+        assert_eq!(half.fill_ratio(), 0.5);
+        assert_eq!(half.phase(), Phase::Midgame);
+
+        let full = play(&DRAWN_GAME);
+        assert_eq!(full.filled(), 42);
+        assert_eq!(full.fill_ratio(), 1.0);
+        assert_eq!(full.phase(), Phase::Endgame);
+    }
+
+    #[test]
+    fn move_numbers_replace_the_discs_they_placed() {
+        plain();
+        let mut game = play(&[4, 4, 3, 5, 2, 1, 4, 4, 3, 3]);
+        game.display.show_move_numbers = true;
+        game.display.grow_up = true;
+
+        assert_eq!(
+            game.render_board(),
+            "--------------------
+CONNECT 4 (Move 10, opening, 24% full)
+P1 longest: 3 | P2 longest: 3
+--------------------
+1  2  3  4  5  6  7
+⚫ ⚫ ⚫ 8  ⚫ ⚫ ⚫
+⚫ ⚫ 10 7  ⚫ ⚫ ⚫
+⚫ ⚫ 9  2  ⚫ ⚫ ⚫
+6  5  3  1  4  ⚫ ⚫
+--------------------
+"
+        );
+    }
+
+    #[test]
+    fn diagonals_of_five_ignore_a_diagonal_four_but_not_a_horizontal_one() {
+        // Player 1 builds a rising diagonal from column 1 and completes its
+        // fourth disc on move 13 and its fifth on move 17.
+        let moves = [1, 2, 4, 3, 2, 5, 3, 4, 5, 4, 3, 5, 4, 7, 5, 7, 5];
+        let five = || GameConfigBuilder::new().win_lengths(4, 5);
+
+        assert!(matches!(
+            play(&moves[..13]).outcome(),
+            GameOutcome::Win(Player::One)
+        ));
+        assert_eq!(
+            build_with(five(), &moves[..16]).outcome(),
+            GameOutcome::Ongoing
+        );
+        assert_eq!(
+            build_with(five(), &moves).outcome(),
+            GameOutcome::Win(Player::One)
+        );
+        assert_eq!(
+            build_with(five(), &[1, 1, 2, 2, 3, 3, 4]).outcome(),
+            GameOutcome::Win(Player::One)
+        );
+    }
+
+    #[test]
+    fn verify_replay_points_at_the_first_illegal_move() {
+        let game = Game::verify_replay(&[4, 4, 3]).unwrap_or_else(|(index, err)| {
+            panic!("move {}: {}", index, err);
+        });
+        assert!(game.board == play(&[4, 4, 3]).board);
+
+        assert!(matches!(
+            Game::verify_replay(&[1, 1, 1, 1, 1, 1, 1, 2]),
+            Err((6, MoveError::ColumnFull))
+        ));
+        assert!(matches!(
+            Game::verify_replay(&[4, 8, 3]),
+            Err((1, MoveError::InvalidColumn { max: 7 }))
+        ));
+        assert!(matches!(
+            Game::verify_replay(&[1, 2, 1, 2, 1, 2, 1, 2]),
+            Err((7, MoveError::GameFinished))
+        ));
+    }
+
+    #[test]
+    fn exact_length_turns_down_overlines_and_short_lines() {
+        let exact = || GameConfigBuilder::new().exact_length(true);
+
+        // Column 3 joins two pairs into five across the bottom.
+        let overline = build_with(exact(), &[1, 1, 2, 2, 4, 4, 5, 5, 3]);
+        assert_eq!(overline.outcome(), GameOutcome::Ongoing);
+        assert_eq!(overline.longest_run(Player::One), 5);
+        assert_eq!(
+            build_with(exact(), &[1, 1, 2, 2, 3, 3, 4]).outcome(),
+            GameOutcome::Win(Player::One)
+        );
+
+        let exact_five = || exact().win_lengths(5, 5);
+        assert_eq!(
+            build_with(exact_five(), &[1, 1, 2, 2, 3, 3, 4]).outcome(),
+            GameOutcome::Ongoing
+        );
+        assert_eq!(
+            build_with(exact_five(), &[1, 1, 2, 2, 3, 3, 4, 7, 5]).outcome(),
+            GameOutcome::Win(Player::One)
+        );
+    }
+
+    #[test]
+    fn output_that_is_not_a_terminal_gets_no_colour_codes() {
+        let dir = scratch_dir("ansi");
+        let file = std::fs::File::create(dir.join("out.txt")).unwrap();
+
+        assert!(!ansi_for(&file, None));
+        assert!(ansi_for(&file, Some(true)));
+        assert!(!ansi_for(&file, Some(false)));
+
+        plain();
+        assert_eq!(escape(RED), "");
+        let mut game = play(&[4, 4, 3]);
+        game.display.show_hud = true;
+        game.display.show_move_numbers = true;
+        assert!(!game.render_board().contains('\x1b'));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn ordered_moves_put_wins_then_blocks_before_the_centre() {
+        let columns = |numbers: &[usize]| -> Vec<Column> {
+            numbers.iter().map(|&number| Column(number - 1)).collect()
+        };
+
+        assert_eq!(
+            Game::default().ordered_moves(Player::One),
+            columns(&[4, 3, 5, 2, 6, 1, 7])
+        );
+
+        // Player 1 can win in column 1, and Player 2 in column 7.
+        let game = play(&[1, 7, 1, 7, 1, 7]);
+        assert_eq!(
+            game.ordered_moves(Player::One),
+            columns(&[1, 7, 4, 3, 5, 2, 6])
+        );
+        assert_eq!(
+            game.ordered_moves(Player::Two),
+            columns(&[7, 1, 4, 3, 5, 2, 6])
+        );
+
+        let full_column = play(&[4, 4, 4, 4, 4, 4]);
+        assert_eq!(
+            full_column.ordered_moves(Player::One),
+            columns(&[3, 5, 2, 6, 1, 7])
+        );
+    }
+
+    #[test]
+    fn declining_a_confirmation_leaves_the_game_as_it_was() {
+        plain();
+        let game = play(&[4, 3]);
+        let before = game.clone();
+        let answer = |text: &'static str| move || Ok(text.to_string());
+
+        assert!(!confirm_move(&game, Column(3), answer("n\n")).unwrap());
+        assert!(!confirm_move(&game, Column(3), answer("\n")).unwrap());
+        assert!(game.board == before.board);
+        assert_eq!(game.current_move, before.current_move);
+        assert_eq!(game.current_player, before.current_player);
+        assert_eq!(game.history, before.history);
+
+        assert!(confirm_move(&game, Column(3), answer("Y\n")).unwrap());
+        assert!(matches!(
+            confirm_move(&game, Column(3), || Err(GameError::InputClosed)),
+            Err(GameError::InputClosed)
+        ));
+
+        // A full column is left for the move itself to turn down.
+        let full = play(&[4, 4, 4, 4, 4, 4]);
+        assert!(confirm_move(&full, Column(3), || panic!("nothing to confirm")).unwrap());
+    }
+
+    #[test]
+    fn a_four_in_the_last_empty_cell_is_a_win_not_a_draw() {
+        let moves = [
+            5, 5, 5, 7, 5, 3, 4, 5, 1, 7, 2, 6, 2, 5, 6, 2, 1, 6, 3, 1, 1, 1, 1, 6, 2, 4, 3, 4, 2,
+            4, 4, 6, 2, 4, 3, 3, 7, 3, 7, 7, 7, 6,
+        ];
+
+        assert_eq!(play(&moves[..41]).outcome(), GameOutcome::Ongoing);
+
+        let game = play(&moves);
+        assert!(game.available_columns().is_empty());
+        assert_eq!(game.outcome(), GameOutcome::Win(Player::Two));
+    }
+
+    #[test]
+    fn a_four_across_the_side_edges_only_wins_when_wrapping() {
+        // Player 1 holds columns 6, 7, 1 and 2 along the bottom.
+        let moves = [6, 6, 7, 7, 1, 1, 2];
+        let wrapped = || GameConfigBuilder::new().wrap(true);
+
+        assert_eq!(play(&moves).outcome(), GameOutcome::Ongoing);
+        assert_eq!(
+            build_with(wrapped(), &moves).outcome(),
+            GameOutcome::Win(Player::One)
+        );
+        assert_eq!(
+            build_with(wrapped(), &moves[..6]).outcome(),
+            GameOutcome::Ongoing
+        );
+
+        // A row filled all the way round still ends at the board's width.
+        let mut ring = build_with(wrapped(), &[]);
+        ring.board[5] = [Player::One as u8; BOARD_WIDTH];
+        assert_eq!(ring.run_length(5, 3, 0, 1), BOARD_WIDTH as u8);
+    }
+
+    #[test]
+    fn win_efficiency_counts_moves_past_the_fastest_forced_win() {
+        // Column 5 opens an unstoppable three along the bottom.
+        assert_eq!(play(&[4, 1, 3, 7, 5, 2, 6]).win_efficiency(), Some(0));
+        assert_eq!(play(&[4, 1, 3, 7, 5, 2, 7, 1, 6]).win_efficiency(), Some(1));
+
+        assert_eq!(play(&[4, 1, 3, 7, 5]).win_efficiency(), None);
+        assert_eq!(play(&DRAWN_GAME).win_efficiency(), None);
+    }
+
+    #[test]
+    fn an_edited_rated_log_fails_verification() {
+        plain();
+        let dir = scratch_dir("rated");
+        let path = dir.join("rated.log");
+        let path = path.to_str().unwrap();
+
+        let mut log = RatedLog::open(path).unwrap();
+        let mut game = Game::default();
+        for column in [Column(3), Column(2), Column(3)] {
+            game.play_move(column).unwrap();
+            log.on_move(&game, game.last_mover(), column, (0, 0));
+        }
+        let certificate = log.hash;
+        drop(log);
+
+        let text = std::fs::read_to_string(path).unwrap();
+        assert_eq!(text.lines().count(), 3);
+        assert_eq!(verify_log(&text), Ok(certificate));
+
+        let edited = text.replacen("move 2 column 3", "move 2 column 4", 1);
+        assert_eq!(verify_log(&edited), Err(2));
+        let dropped: String = text
+            .lines()
+            .skip(1)
+            .map(|line| format!("{}\n", line))
+            .collect();
+        assert_eq!(verify_log(&dropped), Err(1));
+        let mut flipped = text.clone().into_bytes();
+        let last = flipped.len() - 2;
+        flipped[last] = if flipped[last] == b'0' { b'1' } else { b'0' };
+        assert_eq!(verify_log(&String::from_utf8(flipped).unwrap()), Err(3));
+
+        std::fs::write(path, edited).unwrap();
+        assert!(RatedLog::open(path).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn from_bytes_reads_a_row_major_board() {
+        let mut bytes = [0u8; 42];
+        bytes[38] = 1;
+        bytes[39] = 2;
+        bytes[31] = 1;
+
+        let game = Game::from_bytes(bytes, 7, 6).unwrap();
+        assert_eq!(game.board[5][3], 1);
+        assert_eq!(game.board[5][4], 2);
+        assert_eq!(game.board[4][3], 1);
+        assert_eq!(game.current_player, Player::Two);
+        assert_eq!(game.current_move, 3);
+
+        assert!(matches!(
+            Game::from_bytes(&bytes[..41], 7, 6),
+            Err(ValidationError::WrongLength(41))
+        ));
+        assert!(matches!(
+            Game::from_bytes(bytes, 6, 7),
+            Err(ValidationError::WrongDimensions(6, 7))
+        ));
+
+        let mut bad_byte = bytes;
+        bad_byte[0] = 3;
+        assert!(matches!(
+            Game::from_bytes(bad_byte, 7, 6),
+            Err(ValidationError::InvalidCell(3))
+        ));
+
+        let mut floating = bytes;
+        floating[38] = 0;
+        floating[30] = 2;
+        assert!(matches!(
+            Game::from_bytes(floating, 7, 6),
+            Err(ValidationError::FloatingDisc)
+        ));
+    }
+
+    #[test]
+    fn binary_load_rejects_a_foreign_file_and_an_illegal_move() {
+        let mut bytes = binary_save(&play(&[4]));
+        bytes[0] = b'X';
+        assert!(matches!(
+            Game::load_binary(&mut &bytes[..]),
+            Err(LoadError::BadMagic)
+        ));
+
+        let mut overfilled = play(&[4, 4, 4, 4, 4, 4]);
+        overfilled.history.push(Column(3));
+        assert!(matches!(
+            Game::load_binary(&mut &binary_save(&overfilled)[..]),
+            Err(LoadError::InvalidMove(6, MoveError::ColumnFull))
+        ));
+    }
+
+    #[test]
+    fn bundled_challenges_come_easiest_first() {
+        let challenges = challenges_by_difficulty();
+
+        assert_eq!(challenges.len(), CHALLENGES.len());
+        assert!(challenges.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        for (name, _) in CHALLENGES {
+            assert!(challenges.iter().any(|&(sorted, _)| sorted == name));
+        }
+
+        let one_move_win = play(&[4, 1, 4, 1, 4, 7]);
+        let two_move_win = play(&[4, 1, 3, 7]);
+        assert!(one_move_win.difficulty_score() < two_move_win.difficulty_score());
+    }
+
+    #[test]
+    fn swap2_takes_each_colour_or_places_more() {
+        let mut swap2 = Swap2::start(true, &Game::default());
+        assert_eq!(Swap2::start(false, &Game::default()), Swap2::Done);
+        assert_eq!(Swap2::start(true, &play(&[4])), Swap2::Done);
+
+        for _ in 0..3 {
+            assert!(matches!(swap2, Swap2::Placing { seat: 0, .. }));
+            assert_eq!(swap2.choose(Column(0)), None);
+            swap2 = swap2.placed();
+        }
+        let offer = Swap2::Choosing {
+            seat: 1,
+            can_place: true,
+        };
+        assert_eq!(swap2, offer);
+
+        // Seat 2 taking red swaps the seats, taking yellow keeps them.
+        assert_eq!(offer.choose(Column(0)), Some((Swap2::Done, true)));
+        assert_eq!(offer.choose(Column(1)), Some((Swap2::Done, false)));
+        assert_eq!(offer.choose(Column(3)), None);
+
+        let (mut swap2, swapped) = offer.choose(Column(2)).unwrap();
+        assert!(!swapped);
+        assert_eq!(
+            swap2,
+            Swap2::Placing {
+                seat: 1,
+                remaining: 2
+            }
+        );
+        swap2 = swap2.placed().placed();
+        let last_offer = Swap2::Choosing {
+            seat: 0,
+            can_place: false,
+        };
+        assert_eq!(swap2, last_offer);
+        assert_eq!(last_offer.choose(Column(2)), None);
+        assert_eq!(last_offer.choose(Column(0)), Some((Swap2::Done, false)));
+        assert_eq!(last_offer.choose(Column(1)), Some((Swap2::Done, true)));
+        assert_eq!(Swap2::Done.placed(), Swap2::Done);
+    }
+
+    #[test]
+    fn fill_times_never_run_backwards_in_move_order() {
+        let mut game = Game::default();
+        for column in [4, 4, 3, 5, 2, 6, 4, 1].map(|number| Column(number - 1)) {
+            game.play_move(column).unwrap();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let mut filled: Vec<(u8, Duration)> = Vec::new();
+        for (row, numbers) in game.move_numbers.iter().enumerate() {
+            for (col, &number) in numbers.iter().enumerate() {
+                match game.fill_times()[row][col] {
+                    Some(time) => filled.push((number, time)),
+                    None => assert_eq!(number, 0),
+                }
+            }
+        }
+        filled.sort();
+
+        assert_eq!(filled.len(), 8);
+        assert!(filled.windows(2).all(|pair| pair[0].1 < pair[1].1));
+        assert!(filled.iter().map(|&(number, _)| number).eq(1..=8));
+    }
+
+    fn parse_args(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn a_handicap_is_dropped_for_player_one_before_play() {
+        let options = parse_args(&["--handicap", "4,4,3"]).unwrap();
+        assert_eq!(options.handicap, [Column(3), Column(3), Column(2)]);
+        assert!(parse_args(&["--handicap", "4,8"]).is_err());
+
+        let mut game = GameConfigBuilder::new()
+            .handicap(options.handicap)
+            .build()
+            .unwrap_or_else(|err| panic!("{}", err));
+        assert_eq!(game.board[5], [0, 0, 1, 1, 0, 0, 0]);
+        assert_eq!(game.board[4], [0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(game.current_player, Player::Two);
+        assert_eq!(game.move_numbers, [[0; BOARD_WIDTH]; BOARD_HEIGHT]);
+        assert!(game.history.is_empty());
+
+        game.play_move(Column(3)).unwrap();
+        assert_eq!(game.board[3][3], 2);
+        assert_eq!(game.current_player, Player::One);
+
+        let winning = GameConfigBuilder::new()
+            .handicap(vec![Column(0); 4])
+            .build();
+        assert!(matches!(winning, Err(ConfigError::WinningHandicap)));
+    }
+
+    #[test]
+    fn dead_cells_are_the_empty_cells_no_line_can_reach() {
+        let game = diagram(
+            "...O...
+             ...XO..
+             ...OX.O
+             ...OO.X
+             .X.XX.O
+             XX.OO.X",
+        );
+
+        assert_eq!(game.dead_cells(), [(1, 6)]);
+        assert!(!game.is_dead_draw());
+        assert!(Game::default().dead_cells().is_empty());
+        assert!(play(&[4, 4, 3]).dead_cells().is_empty());
+    }
+
+    #[test]
+    fn center_gravity_fills_a_column_from_the_middle_out() {
+        let mut game = build_with(GameConfigBuilder::new().center_gravity(true), &[]);
+        let mut landed = Vec::new();
+
+        for _ in 0..BOARD_HEIGHT {
+            game.play_move(Column(3)).unwrap();
+            landed.push(game.last_landing().unwrap());
+        }
+
+        // Rows count from the top, so the lower middle row is 3.
+        assert_eq!(landed, [(3, 3), (2, 3), (4, 3), (1, 3), (5, 3), (0, 3)]);
+        assert!(matches!(
+            game.play_move(Column(3)),
+            Err(MoveError::ColumnFull)
+        ));
+        assert_eq!(
+            game.board_view().column(3),
+            [1, 1, 1, 2, 2, 2].map(Player::from_int)
+        );
+    }
+
+    #[test]
+    fn key_moments_are_swings_past_the_threshold() {
+        assert_eq!(
+            key_moments(&[0, 2, -50, -48, 100, 106], 6),
+            [(1, -52), (3, 148)]
+        );
+        assert!(key_moments(&[0], 6).is_empty());
+        assert!(key_moments(&[], 6).is_empty());
+
+        let game = play(&[4, 1, 4, 1, 4, 7, 4]);
+        let evals = [0, 0, 0, 10, 10, 10, WIN_SCORE, WIN_SCORE];
+        assert_eq!(
+            game.describe_key_moments(&evals, KEY_MOMENT_SWING),
+            [
+                "move 3 turned the game: eval swung from +0 to +10",
+                "move 6 was the losing blunder: eval swung from +10 to a forced win for P1",
+            ]
+        );
+    }
+
+    #[test]
+    fn describe_reads_the_board_out_column_by_column() {
+        let game = diagram(
+            ".......
+             .......
+             .......
+             .......
+             ....O..
+             XXX.OO.",
+        );
+
+        assert_eq!(
+            game.describe(),
+            "Column 1: red, then 5 empty.
+Column 2: red, then 5 empty.
+Column 3: red, then 5 empty.
+Column 4: empty.
+Column 5: yellow, yellow, then 4 empty.
+Column 6: yellow, then 5 empty.
+Column 7: empty.
+Player 1 to move, playing red.
+Player 1 threatens column 4 row 1.
+"
+        );
+        assert!(play(&[1, 2, 1, 2, 1, 2, 1])
+            .describe()
+            .contains("The game is over."));
+    }
+
+    #[test]
+    fn quiet_output_leaves_only_the_result() {
+        plain();
+        let game = play(&[1, 2, 1, 2, 1, 2, 1]);
+        let output = |level| {
+            let mut out = Vec::new();
+            game.write_board(&mut out, level).unwrap();
+            write_menu(&mut out, &game, level, true).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(output(Verbosity::Quiet), format!("{}\n", game.result()));
+
+        let normal = output(Verbosity::Normal);
+        assert!(normal.starts_with(&game.render_board()));
+        assert!(normal.contains("Press 'R' to restart"));
+        assert!(!normal.contains(&format!("{}\n", game.result())));
+
+        let mut out = Vec::new();
+        write_menu(&mut out, &game, Verbosity::Quiet, false).unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn winner_if_any_only_reads_the_board() {
+        let mut game = play(&[1, 2, 1, 2, 1, 2, 1]);
+        game.is_finished = false;
+        game.winner = Player::None;
+
+        assert_eq!(game.winner_if_any(), Some(Player::One));
+        assert!(!game.is_finished);
+        assert_eq!(game.winner, Player::None);
+
+        assert_eq!(play(&[1, 2, 1, 2, 1, 2]).winner_if_any(), None);
+        assert_eq!(play(&DRAWN_GAME).winner_if_any(), None);
+
+        assert_eq!(game.calculate_winner(), Player::One);
+        assert!(game.is_finished);
+    }
+
+    #[test]
+    fn pressure_adds_threats_centre_and_tempo() {
+        // Player 1 has a threat square in column 4 and the move.
+        let pressing = diagram(
+            ".......
+             .......
+             .......
+             .......
+             ....O..
+             XXX.OO.",
+        );
+        assert_eq!(pressing.pressure(Player::One), 3);
+        assert_eq!(pressing.pressure(Player::Two), -3);
+
+        // Level in the centre, so only having the move counts.
+        let balanced = play(&[4, 4]);
+        assert_eq!(balanced.pressure(Player::One), 1);
+        assert_eq!(balanced.pressure(Player::Two), -1);
+        assert_eq!(balanced.pressure(Player::None), 0);
+
+        let centre = play(&[4, 1, 4, 1]);
+        assert_eq!(centre.pressure(Player::One), 3);
+    }
+
+    #[test]
+    fn repeated_and_concurrent_saves_always_leave_a_loadable_file() {
+        let dir = scratch_dir("saves");
+        let path = dir.join("game.c4");
+        let path = path.to_str().unwrap();
+        let game = play(&[4, 4, 3, 5, 2]);
+
+        write_save(&game, path).unwrap();
+        let first = std::fs::read(path).unwrap();
+        for _ in 0..20 {
+            write_save(&game, path).unwrap();
+            assert_eq!(std::fs::read(path).unwrap(), first);
+        }
+
+        let savers: Vec<_> = (0..4)
+            .map(|_| {
+                let game = game.clone();
+                let path = path.to_string();
+                std::thread::spawn(move || {
+                    for _ in 0..20 {
+                        write_save(&game, &path).unwrap();
+                        let loaded = load_game(&path).unwrap();
+                        assert_eq!(loaded.history, game.history);
+                    }
+                })
+            })
+            .collect();
+        for saver in savers {
+            saver.join().unwrap();
+        }
+
+        assert_eq!(std::fs::read(path).unwrap(), first);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn postgame_analysis_is_recorded_quietly_and_reported_at_the_end() {
+        plain();
+        let mut game = Game::default();
+        let mut recorded = Vec::new();
+        record_eval(&mut recorded, &game);
+        for column in [4, 1, 4, 1, 4, 7, 4].map(|number| Column(number - 1)) {
+            game.play_move(column).unwrap();
+            record_eval(&mut recorded, &game);
+        }
+        assert_eq!(recorded.len(), 8);
+        assert!(recorded.iter().all(Option::is_some));
+
+        let report = postgame_report(&game, &recorded, false);
+        assert!(report.starts_with("Post-game analysis:\n"));
+        assert!(report.contains(&game.annotate_game()));
+        assert!(report.contains("6. P2 column 7: blunder, allows a win in column 4\n"));
+        let evals = report
+            .lines()
+            .find_map(|line| line.strip_prefix("Evaluations: "))
+            .unwrap();
+        assert_eq!(evals.split(' ').count(), 8);
+        assert!(report.contains("move 6 was the losing blunder"));
+
+        let annotated = postgame_report(&game, &recorded, true);
+        assert!(!annotated.contains("6. P2 column 7"));
+        assert!(annotated.contains("Evaluations: "));
+
+        let level = postgame_report(&game, &[Some(0); 8], true);
+        assert!(level.contains("Evaluations: +0 +0 +0 +0 +0 +0 +0 +0\n"));
+
+        assert!(parse_args(&["--postgame-analysis", "--analyze"]).is_err());
+    }
+
+    #[test]
+    fn input_history_recalls_valid_lines_and_stops_at_the_ends() {
+        let mut history = InputHistory::new(3);
+        assert_eq!(history.older(), None);
+        assert_eq!(history.newer(), None);
+
+        for line in ["4", "banana", "3", "3", "u", "5"] {
+            history.push(line);
+        }
+
+        // The oldest of four valid entries fell off; the typo and the repeat
+        // were never kept.
+        assert_eq!(history.older(), Some("5"));
+        assert_eq!(history.older(), Some("u"));
+        assert_eq!(history.older(), Some("3"));
+        assert_eq!(history.older(), Some("3"));
+        assert_eq!(history.newer(), Some("u"));
+        assert_eq!(history.newer(), Some("5"));
+        assert_eq!(history.newer(), Some(""));
+        assert_eq!(history.newer(), None);
+
+        history.older();
+        history.push("6");
+        assert_eq!(history.older(), Some("6"));
+    }
+
+    #[test]
+    fn draw_offers_are_answered_by_the_opponent_with_a_cooldown() {
+        // Player 2 offers with their move, and Player 1 answers on theirs.
+        let offered = || {
+            let mut game = play(&[4]);
+            game.offer_draw().unwrap();
+            assert!(matches!(
+                game.offer_draw(),
+                Err(MoveError::DrawAlreadyOffered)
+            ));
+            assert!(!game.draw_offered());
+            assert!(matches!(game.accept_draw(), Err(MoveError::NoDrawOffer)));
+            game.play_move(Column(3)).unwrap();
+            assert!(game.draw_offered());
+            game
+        };
+
+        let mut accepted = offered();
+        accepted.accept_draw().unwrap();
+        assert_eq!(accepted.outcome(), GameOutcome::Draw);
+        assert!(matches!(
+            accepted.offer_draw(),
+            Err(MoveError::GameFinished)
+        ));
+
+        let mut declined = offered();
+        declined.decline_draw().unwrap();
+        assert_eq!(declined.outcome(), GameOutcome::Ongoing);
+        assert!(!declined.draw_offered());
+        assert!(matches!(
+            declined.decline_draw(),
+            Err(MoveError::NoDrawOffer)
+        ));
+
+        declined.play_move(Column(2)).unwrap();
+        assert!(matches!(
+            declined.offer_draw(),
+            Err(MoveError::DrawOfferCooldown { moves: 4 })
+        ));
+        for column in [2, 2, 2, 5] {
+            declined.play_move(Column(column)).unwrap();
+        }
+        assert_eq!(declined.current_move, DRAW_OFFER_COOLDOWN + 1);
+        assert_eq!(declined.current_player, Player::Two);
+        declined.offer_draw().unwrap();
+    }
+
+    #[test]
+    fn column_lock_plays_on_the_second_press() {
+        plain();
+        let mut locked = None;
+
+        assert_eq!(lock_column(&mut locked, Column(3)), None);
+        assert_eq!(locked, Some(Column(3)));
+        assert_eq!(lock_column(&mut locked, Column(5)), None);
+        assert_eq!(locked, Some(Column(5)));
+        assert_eq!(lock_column(&mut locked, Column(5)), Some(Column(5)));
+        assert_eq!(locked, None);
+
+        let mut game = play(&[4]);
+        assert_eq!(lock_column(&mut game.locked_column, Column(1)), None);
+        assert!(game.render_board().contains("   ▼"));
+        assert_eq!(game.history.len(), 1);
+    }
+
+    #[test]
+    fn principal_variations_replay_legally_and_stop_at_the_end() {
+        let mut rng = Rng::from_seed(7);
+        for _ in 0..5 {
+            let start = random_game(&mut rng);
+            let line = start.principal_variation(PV_DEPTH, &ThreatEvaluator);
+            let mut game = start.clone();
+
+            for &column in &line {
+                assert!(!game.is_finished);
+                game.play_move(column).unwrap();
+            }
+            assert!(line.len() == PV_DEPTH as usize || game.is_finished);
+        }
+
+        // Player 1 wins on the spot, so the line is just that move.
+        let winning = play(&[4, 1, 4, 1, 4, 7]);
+        let line = winning.principal_variation(PV_DEPTH, &ThreatEvaluator);
+        assert_eq!(line, [Column(3)]);
+        let mut game = winning.clone();
+        game.play_move(line[0]).unwrap();
+        assert_eq!(game.outcome(), GameOutcome::Win(Player::One));
+
+        // A double threat is won two moves later whatever Player 2 does.
+        let forced = play(&[4, 1, 3, 7]);
+        let line = forced.principal_variation(PV_DEPTH, &ThreatEvaluator);
+        let mut game = forced.clone();
+        for &column in &line {
+            game.play_move(column).unwrap();
+        }
+        assert_eq!(line.len(), 3);
+        assert_eq!(game.outcome(), GameOutcome::Win(Player::One));
+    }
+
+    #[test]
+    fn mercy_ends_the_game_only_once_the_lead_cannot_be_caught() {
+        // Player 1's three along the bottom scores columns 1 and 5, and
+        // Player 2 has nothing to build on.
+        let moves = [2, 2, 3, 7, 4];
+        let scored = |limit, mercy| {
+            build_with(
+                GameConfigBuilder::new()
+                    .turn_limit(Some(limit))
+                    .mercy(Some(mercy)),
+                &moves,
+            )
+        };
+
+        // With one move left, Player 2 can fill one square but not both.
+        let hopeless = scored(6, 2);
+        assert!(hopeless.is_finished);
+        assert_eq!(hopeless.winner, Player::One);
+        assert_eq!(hopeless.current_move, 5);
+
+        // Two moves left could fill both squares, a longer game even more,
+        // and a larger margin isn't reached at all.
+        assert!(!scored(7, 2).is_finished);
+        assert!(!scored(20, 2).is_finished);
+        assert!(!scored(6, 3).is_finished);
+    }
+
+    #[test]
+    fn move_iter_yields_each_legal_move_in_fill_order() {
+        let start = play(&[4, 4, 4, 4, 4, 4, 1]);
+        let moves: Vec<(Column, Game)> = start.move_iter().collect();
+
+        let columns: Vec<Column> = moves.iter().map(|&(column, _)| column).collect();
+        assert_eq!(columns, [2, 4, 1, 5, 0, 6].map(Column));
+        for (column, after) in &moves {
+            assert_eq!(after.history.len(), start.history.len() + 1);
+            assert_eq!(after.history.last(), Some(column));
+        }
+        assert_eq!(start.history.len(), 7);
+
+        assert_eq!(Game::default().move_iter().count(), BOARD_WIDTH);
+        assert_eq!(play(&[1, 2, 1, 2, 1, 2, 1]).move_iter().count(), 0);
+        assert_eq!(play(&DRAWN_GAME).move_iter().count(), 0);
+    }
+
+    #[test]
+    fn compare_replays_finds_the_first_ply_that_differs() {
+        let game = play(&[4, 4, 3, 5]);
+        assert_eq!(game.compare_replays(&game.clone()), None);
+
+        assert_eq!(
+            game.compare_replays(&play(&[4, 4, 2, 5])),
+            Some((3, "column 3 against column 2".to_string()))
+        );
+        assert_eq!(
+            game.compare_replays(&play(&[4, 4, 3])),
+            Some((4, "column 5 against the end of the second game".to_string()))
+        );
+        assert_eq!(
+            play(&[4]).compare_replays(&play(&[4, 1])),
+            Some((2, "the end of the first game against column 1".to_string()))
+        );
+
+        let started = diagram(
+            ".......
+             .......
+             .......
+             .......
+             .......
+             X......",
+        );
+        assert_eq!(
+            started.compare_replays(&Game::default()),
+            Some((0, "the starting positions differ".to_string()))
+        );
+    }
+
+    #[test]
+    fn bounce_rolls_into_a_free_neighbour_or_fails_without_one() {
+        let bouncing = |bounce| {
+            build_with(
+                GameConfigBuilder::new().bounce(Some(bounce)),
+                &[2, 2, 2, 2, 2, 2],
+            )
+        };
+
+        let mut left = bouncing(Bounce::Left);
+        left.play_move(Column(1)).unwrap();
+        assert_eq!(left.history.last(), Some(&Column(0)));
+        assert_eq!(left.board[5][0], Player::One as u8);
+
+        let mut right = bouncing(Bounce::Right);
+        right.play_move(Column(1)).unwrap();
+        assert_eq!(right.history.last(), Some(&Column(2)));
+        assert_eq!(right.board[5][2], Player::One as u8);
+
+        let mut boxed_in = build_with(
+            GameConfigBuilder::new().bounce(Some(Bounce::Right)),
+            &[1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2],
+        );
+        assert!(matches!(
+            boxed_in.play_move(Column(0)),
+            Err(MoveError::NoBounce)
+        ));
+        assert_eq!(boxed_in.history.len(), 12);
+    }
+
+    #[test]
+    fn each_frame_style_draws_its_own_rules() {
+        plain();
+        let framed = |style| {
+            let mut game = play(&[4, 4, 3]);
+            game.display.grow_up = true;
+            game.display.frame_style = style;
+            game.render_board()
+        };
+
+        assert_eq!(
+            framed(FrameStyle::Ascii),
+            "--------------------
+CONNECT 4 (Move 3, opening, 7% full)
+P1 longest: 2 | P2 longest: 1
+--------------------
+1  2  3  4  5  6  7
+⚫ ⚫ ⚫ 🟡 ⚫ ⚫ ⚫
+⚫ ⚫ 🔴 🔴 ⚫ ⚫ ⚫
+--------------------
+"
+        );
+        assert_eq!(
+            framed(FrameStyle::Box),
+            "────────────────────
+CONNECT 4 (Move 3, opening, 7% full)
+P1 longest: 2 | P2 longest: 1
+────────────────────
+1  2  3  4  5  6  7
+⚫ ⚫ ⚫ 🟡 ⚫ ⚫ ⚫
+⚫ ⚫ 🔴 🔴 ⚫ ⚫ ⚫
+────────────────────
+"
+        );
+        assert_eq!(
+            framed(FrameStyle::None),
+            "CONNECT 4 (Move 3, opening, 7% full)
+P1 longest: 2 | P2 longest: 1
+1  2  3  4  5  6  7
+⚫ ⚫ ⚫ 🟡 ⚫ ⚫ ⚫
+⚫ ⚫ 🔴 🔴 ⚫ ⚫ ⚫
+"
+        );
+    }
+
+    #[test]
+    fn session_stats_add_up_every_game_recorded() {
+        let mut stats = SessionStats::default();
+        let win = play(&[1, 2, 1, 2, 1, 2, 1]);
+        let loss = play(&[1, 2, 1, 2, 1, 2, 3, 2]);
+
+        assert_eq!(
+            stats.summary(&win),
+            [
+                "Session: 0 games played",
+                "Player 1: 0 wins, 0 losses and 0 draws",
+                "Player 2: 0 wins, 0 losses and 0 draws",
+            ]
+        );
+
+        stats.record(&win);
+        stats.record(&loss);
+        stats.record(&play(&DRAWN_GAME));
+        stats.record(&win);
+        stats.move_time = Duration::from_secs(9);
+        stats.timed_moves = 6;
+
+        assert_eq!(stats.games, 4);
+        assert_eq!(stats.wins, [2, 1]);
+        assert_eq!(stats.draws, 1);
+        assert_eq!(stats.moves, 7 + 8 + 42 + 7);
+        assert_eq!(stats.longest, 42);
+        assert_eq!(
+            stats.summary(&win),
+            [
+                "Session: 4 games played",
+                "Player 1: 2 wins, 1 loss and 1 draw",
+                "Player 2: 1 win, 2 losses and 1 draw",
+                "Average moves per game: 16.0",
+                "Longest game: 42 moves",
+                "Average move time: 1.5s",
+            ]
+        );
+    }
+
+    #[test]
+    fn undoing_against_the_bot_takes_back_its_reply_too() {
+        let drill = parse_args(&["--drill"]).unwrap();
+        let human = parse_args(&[]).unwrap();
+
+        let mut game = play(&[4, 4, 3, 5]);
+        assert_eq!(drill.takeback_plies(&game), 2);
+        assert_eq!(human.takeback_plies(&game), 1);
+        game.undo_turn(drill.takeback_plies(&game)).unwrap();
+        assert!(game.board == play(&[4, 4]).board);
+        assert_eq!(game.current_player, Player::One);
+        assert_eq!(game.redo, [Column(4), Column(2)]);
+
+        let bot_opened = parse_args(&["--p1-script", "moves.txt"]).unwrap();
+        let mut only_the_bot_moved = play(&[4]);
+        assert_eq!(bot_opened.takeback_plies(&only_the_bot_moved), 2);
+        assert!(matches!(
+            only_the_bot_moved.undo_turn(bot_opened.takeback_plies(&only_the_bot_moved)),
+            Err(MoveError::NothingToUndo)
+        ));
+        assert_eq!(only_the_bot_moved.history, [Column(3)]);
+
+        let mut bot_to_move = play(&[4]);
+        assert_eq!(drill.takeback_plies(&bot_to_move), 1);
+        assert!(matches!(
+            bot_to_move.undo_turn(2),
+            Err(MoveError::NothingToUndo)
+        ));
+        assert_eq!(bot_to_move.history, [Column(3)]);
+
+        bot_to_move.undo_turn(1).unwrap();
+        assert!(bot_to_move.board == Game::default().board);
+        assert!(matches!(
+            bot_to_move.undo_turn(drill.takeback_plies(&bot_to_move)),
+            Err(MoveError::NothingToUndo)
+        ));
+        assert!(matches!(
+            Game::default().undo_turn(1),
+            Err(MoveError::NothingToUndo)
+        ));
+    }
+
+    #[test]
+    fn fill_order_must_name_every_column_once_and_then_orders_the_moves() {
+        let rejected = "--fill-order must list each of the 7 columns once";
+        assert_eq!(
+            parse_args(&["--fill-order", "1,2,3,4,5,6,6"])
+                .err()
+                .as_deref(),
+            Some(rejected)
+        );
+        assert_eq!(
+            parse_args(&["--fill-order", "1,2,3,4,5,6"])
+                .err()
+                .as_deref(),
+            Some(rejected)
+        );
+        assert!(parse_args(&["--fill-order", "1,2,3,4,5,6,8"]).is_err());
+
+        let options = parse_args(&["--fill-order", "2,1,7,6,5,4,3"]).unwrap();
+        let game = options
+            .new_config()
+            .moves(vec![Column(1)])
+            .build()
+            .unwrap_or_else(|_| panic!("the move is legal"));
+        assert_eq!(
+            game.ordered_moves(Player::Two),
+            [1, 0, 6, 5, 4, 3, 2].map(Column)
+        );
+
+        let right = parse_args(&["--fill-order", "right"]).unwrap();
+        assert_eq!(right.fill_order, Some((0..7).rev().map(Column).collect()));
+    }
+
+    #[test]
+    fn snapshots_are_plain_text_that_reads_back_as_the_same_board() {
+        let game = play(&[4, 4, 3, 5, 2]);
+        let snapshot = game.render_snapshot();
+
+        assert!(!snapshot.contains('\x1b'));
+        assert_eq!(
+            snapshot,
+            "1234567
+.......
+.......
+.......
+.......
+...O...
+.XXXO..
+Player 2 (O) to move
+"
+        );
+
+        let lines: Vec<&str> = snapshot.lines().collect();
+        let restored = diagram(&lines[1..lines.len() - 1].join("\n"));
+        assert!(restored.board == game.board);
+        assert_eq!(restored.current_player, game.current_player);
+
+        let won = play(&[1, 2, 1, 2, 1, 2, 1]).render_snapshot();
+        assert!(!won.contains('\x1b'));
+        assert!(won.ends_with("\nGame over, 1-0\n"), "{}", won);
+    }
+
+    #[test]
+    fn sgf_records_round_trip_and_reject_malformed_input() {
+        let start = diagram(
+            ".......
+             .......
+             .......
+             .......
+             .......
+             O.....X",
+        );
+        let mut game = GameConfigBuilder::new()
+            .start_board(start.board)
+            .win_lengths(4, 5)
+            .meta(GameMeta {
+                p1_name: Some("Ann [A]".to_string()),
+                p2_name: Some("B\\o".to_string()),
+                ..GameMeta::default()
+            })
+            .moves([4, 4, 3, 5].map(Column).to_vec())
+            .build()
+            .unwrap_or_else(|_| panic!("the moves are legal"));
+        game.play_move(Column(2)).unwrap();
+
+        let restored = Game::from_sgf(&game.to_sgf()).unwrap();
+        assert!(restored.board == game.board);
+        assert_eq!(restored.history, game.history);
+        assert!(restored.start_board == game.start_board);
+        assert_eq!(restored.win_length_diagonal, 5);
+        assert_eq!(restored.meta.p1_name.as_deref(), Some("Ann [A]"));
+        assert_eq!(restored.meta.p2_name.as_deref(), Some("B\\o"));
+        assert_eq!(restored.to_sgf(), game.to_sgf());
+
+        assert!(matches!(
+            Game::from_sgf("(;GM[connect4]WL[four]\n;R[4])"),
+            Err(SgfError::InvalidValue(property, value)) if property == "WL" && value == "four"
+        ));
+        assert!(matches!(
+            Game::from_sgf("(;GM[connect4]\n;R[4]\n;Y[8])"),
+            Err(SgfError::InvalidMove(1, MoveError::InvalidColumn { .. }))
+        ));
+        assert!(matches!(
+            Game::from_sgf("(;GM[connect4]\n;R[4]\n;R[3])"),
+            Err(SgfError::WrongPlayer(1))
+        ));
+        assert!(matches!(
+            Game::from_sgf("(;GM[connect4]\n;R[4"),
+            Err(SgfError::Malformed(_))
+        ));
+        assert!(matches!(
+            Game::from_sgf("(;SZ[7:6]\n;R[4])"),
+            Err(SgfError::NotConnectFour)
+        ));
+
+        let path = scratch_dir("sgf").join("bad.sgf");
+        std::fs::write(&path, "(;GM[connect4]\n;R[0])").unwrap();
+        assert!(matches!(
+            load_game(path.to_str().unwrap()),
+            Err(LoadError::InvalidRecord(SgfError::InvalidMove(0, _)))
+        ));
+    }
+
+    #[test]
+    fn compact_boards_leave_out_the_header_rules() {
+        plain();
+        let options = parse_args(&["--compact"]).unwrap();
+        assert!(options.display.compact);
+
+        let mut game = play(&[4, 4, 3]);
+        let full = game.render_board();
+        game.display = options.display;
+        let compact = game.render_board();
+
+        assert_eq!(full.lines().count(), 12);
+        assert_eq!(compact.lines().count(), 10);
+        assert!(!compact.lines().any(str::is_empty));
+        assert!(compact.starts_with("CONNECT 4 (Move 3"));
+        assert!(compact.ends_with("⚫ ⚫ 🔴 🔴 ⚫ ⚫ ⚫\n--------------------\n"));
+    }
+
+    #[test]
+    fn is_lost_spots_a_double_threat_but_not_a_single_one() {
+        // Player 1 has three in a row along the bottom with both ends open.
+        assert!(play(&[4, 1, 3, 1, 5]).is_lost());
+        assert!(diagram(ONLY_MOVE_LOSES).is_lost());
+
+        // With only two in a row Player 2 can still block either end.
+        assert!(!play(&[4, 1, 3]).is_lost());
+        assert!(!Game::default().is_lost());
+        assert!(!play(&[1, 2, 1, 2, 1, 2, 1]).is_lost());
+    }
+
+    #[test]
+    fn a_zero_win_pause_goes_straight_to_the_menu() {
+        let pause = |args: &[&str]| parse_args(args).unwrap();
+
+        assert_eq!(pause(&[]).win_pause(true), None);
+        assert_eq!(pause(&["--win-pause", "0"]).win_pause(true), None);
+        assert_eq!(
+            pause(&["--win-pause", "250"]).win_pause(true),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(pause(&["--win-pause", "250"]).win_pause(false), None);
+        assert!(parse_args(&["--win-pause", "-1"]).is_err());
+    }
+
+    #[test]
+    fn observers_see_the_reset_every_move_and_the_win_in_order() {
+        struct Recorder(std::rc::Rc<std::cell::RefCell<Vec<String>>>);
+
+        impl GameObserver for Recorder {
+            fn on_reset(&mut self, game: &Game) {
+                self.0
+                    .borrow_mut()
+                    .push(format!("reset at move {}", game.history.len()));
+            }
+
+            fn on_move(
+                &mut self,
+                _game: &Game,
+                player: Player,
+                column: Column,
+                landed: (usize, usize),
+            ) {
+                self.0.borrow_mut().push(format!(
+                    "P{} column {} at {:?}",
+                    player as u8,
+                    column.to_user(),
+                    landed
+                ));
+            }
+
+            fn on_win(&mut self, _game: &Game, winner: Player) {
+                self.0.borrow_mut().push(format!("P{} won", winner as u8));
+            }
+
+            fn on_draw(&mut self, _game: &Game) {
+                self.0.borrow_mut().push("draw".to_string());
+            }
+        }
+
+        let events = std::rc::Rc::default();
+        let mut observers: Vec<Box<dyn GameObserver>> =
+            vec![Box::new(Recorder(std::rc::Rc::clone(&events)))];
+        let mut game = Game::default();
+
+        notify_reset(&mut observers, &game);
+        for number in [1, 2, 1, 2, 1, 2, 1] {
+            game.play_move(Column(number - 1)).unwrap();
+            notify_move(&mut observers, &game);
+        }
+        notify_end(&mut observers, &game);
+
+        assert_eq!(
+            *events.borrow(),
+            [
+                "reset at move 0",
+                "P1 column 1 at (5, 0)",
+                "P2 column 2 at (5, 1)",
+                "P1 column 1 at (4, 0)",
+                "P2 column 2 at (4, 1)",
+                "P1 column 1 at (3, 0)",
+                "P2 column 2 at (3, 1)",
+                "P1 column 1 at (2, 0)",
+                "P1 won",
+            ]
+        );
+    }
+
+    #[test]
+    fn the_daily_puzzle_depends_only_on_the_date() {
+        let options = parse_args(&["--daily"]).unwrap();
+        let puzzle = |date| daily_puzzle(&options, date).unwrap().board;
+
+        assert!(puzzle("2026-10-14") == puzzle("2026-10-14"));
+        assert!(puzzle("2026-10-14") != puzzle("2026-10-15"));
+    }
+
+    #[test]
+    fn the_hud_shows_the_evaluation_threats_and_phase() {
+        plain();
+        assert_eq!(
+            Game::default().render_hud(),
+            "HUD: P1 [##########----------] P2 (+0) | Threats: P1 0, P2 0 | opening"
+        );
+
+        // Player 1's open three along the bottom has a threat square at each end.
+        let mut game = play(&[4, 1, 3, 1, 5]);
+        let hud = "HUD: P1 [###############-----] P2 (+9) | Threats: P1 2, P2 0 | opening";
+        assert_eq!(game.render_hud(), hud);
+        assert!(!game.render_board().contains("HUD:"));
+
+        game.display.show_hud = true;
+        let lines: Vec<String> = game.render_board().lines().map(String::from).collect();
+        assert_eq!(lines[3], hud);
+    }
+
+    #[test]
+    fn a_board_with_nothing_left_to_play_settles_under_the_rules() {
+        // The board fills without a line of five. Player 2 has four in a
+        // row somewhere and Player 1 only three.
+        let moves = [
+            1, 3, 2, 7, 6, 5, 6, 5, 5, 4, 2, 6, 6, 4, 3, 7, 4, 2, 1, 1, 4, 6, 1, 3, 6, 3, 4, 3, 2,
+            2, 3, 5, 7, 2, 5, 7, 1, 1, 7, 4, 7, 5,
+        ];
+        let stuck = |config: GameConfigBuilder| {
+            let mut game = build_with(config.win_lengths(5, 5), &moves);
+            game.is_finished = false;
+            game.winner = Player::None;
+            assert!(game.available_columns().is_empty());
+
+            game.settle();
+            assert!(game.is_finished);
+            game
+        };
+
+        assert_eq!(stuck(GameConfigBuilder::new()).outcome(), GameOutcome::Draw);
+
+        let mut longest_run = stuck(GameConfigBuilder::new().tiebreak(Tiebreak::LongestRun));
+        assert_eq!(longest_run.outcome(), GameOutcome::Win(Player::Two));
+        assert!(matches!(
+            longest_run.play_move(Column(0)),
+            Err(MoveError::GameFinished)
+        ));
+    }
+
+    #[test]
+    fn the_winning_line_goes_with_an_undo_and_comes_back_with_a_redo() {
+        let mut game = play(&DIAGONAL_FOUR[..10]);
+        assert_eq!(game.winning_line(), None);
+
+        game.play_move(Column(3)).unwrap();
+        let line = Some([(5, 0), (4, 1), (3, 2), (2, 3)]);
+        assert_eq!(game.winning_line(), line);
+
+        game.undo().unwrap();
+        assert!(!game.is_finished);
+        assert_eq!(game.winning_line(), None);
+
+        game.redo().unwrap();
+        assert!(game.is_finished);
+        assert_eq!(game.winning_line(), line);
+
+        assert_eq!(
+            play(&[1, 2, 1, 2, 1, 2, 1]).winning_line(),
+            Some([(2, 0), (3, 0), (4, 0), (5, 0)])
+        );
+        assert_eq!(play(&DRAWN_GAME).winning_line(), None);
+    }
+
+    #[test]
+    fn the_threat_tally_follows_the_move_just_played() {
+        plain();
+        let mut game = parse_args(&["--threat-count"])
+            .unwrap()
+            .new_config()
+            .moves([3, 0, 2, 0].map(Column).to_vec())
+            .build()
+            .unwrap_or_else(|_| panic!("the moves are legal"));
+        assert_eq!(
+            game.render_status(),
+            "Player 1 to move | Move 4 | Threats — P1: 0  P2: 0"
+        );
+
+        // Three along the bottom threatens both ends at once.
+        game.play_move(Column(4)).unwrap();
+        assert_eq!(
+            game.render_status(),
+            "Player 2 to move | Move 5 | Threats — P1: 2  P2: 0"
+        );
+
+        // Blocking one end leaves the other.
+        game.play_move(Column(1)).unwrap();
+        assert_eq!(
+            game.render_status(),
+            "Player 1 to move | Move 6 | Threats — P1: 1  P2: 0"
+        );
+    }
+
+    #[test]
+    fn first_blunder_finds_the_one_move_that_threw_the_game() {
+        // Player 2 leaves both ends of Player 1's pair open with move 4,
+        // after which Player 1 can force a double threat.
+        let game = play(&[4, 1, 3, 1, 5, 2, 6]);
+        let evals = game.searched_evals(&[]);
+        assert_eq!(game.first_blunder(&evals, KEY_MOMENT_SWING), Some(3));
+        assert_eq!(key_moments(&evals, KEY_MOMENT_SWING).len(), 1);
+
+        // The same three along the bottom, blocked at each end in time.
+        let clean = play(&[4, 4, 3, 2, 5, 6]);
+        let evals = clean.searched_evals(&[]);
+        assert_eq!(clean.first_blunder(&evals, KEY_MOMENT_SWING), None);
+    }
+}